@@ -0,0 +1,270 @@
+// cli.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::{bail, Context, Error, Result};
+use std::path::{Path, PathBuf};
+
+use metanote_core::error::MetadataError;
+use metanote_core::field::Field;
+use metanote_core::metadata::{
+    FieldValue, MetadataAgent, MetadataContainer, MetadataEdit, MetadataReadCapable,
+    MetadataWriteCapable,
+};
+
+const USAGE: &str = "\
+Usage:
+  metanote-cli get FILE...                      Print the tags of each file
+  metanote-cli set --FIELD VALUE... FILE...     Set fields on each file
+  metanote-cli clear --field FIELD... FILE...   Remove fields from each file
+
+Fields:
+  title, artist, album-artist, album, track, track-total, genre, year,
+  disc, disc-total, composer, comment, copyright, art (clear only)
+
+Extra fields:
+  subtitle, disc-subtitle, grouping, original-album, original-artist,
+  original-lyricist, title-sort, artist-sort, album-sort, album-artist-sort,
+  composer-sort, lyricist, writer, arranger, conductor, performer,
+  producer, engineer, mix-engineer, mix-dj, remixer, publisher, label,
+  catalog-number, isrc, barcode, original-date, bpm, key, mood, language,
+  script, description, lyrics, license, encoded-by, encoder, encoder-settings";
+
+/// A command, parsed from the command line
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Get(Vec<PathBuf>),
+    Set(MetadataEdit, Vec<PathBuf>),
+    Clear(MetadataEdit, Vec<PathBuf>),
+    Help,
+}
+
+impl Command {
+    /// Parses the arguments following the program name
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let (command, rest) = args.split_first().context("no command given")?;
+
+        match command.as_str() {
+            "get" => Self::parse_get(rest),
+            "set" => Self::parse_set(rest),
+            "clear" => Self::parse_clear(rest),
+            "help" | "--help" | "-h" => Ok(Command::Help),
+            _ => bail!("unknown command {command}"),
+        }
+    }
+
+    fn parse_get(args: &[String]) -> Result<Self> {
+        Ok(Command::Get(Self::paths(args)?))
+    }
+
+    fn parse_set(args: &[String]) -> Result<Self> {
+        let mut edit = MetadataEdit::default();
+        let mut args = args.iter().peekable();
+
+        while let Some(field) = args.peek().and_then(|a| a.strip_prefix("--")) {
+            args.next();
+            let value = args
+                .next()
+                .with_context(|| format!("missing value for --{field}"))?;
+            set_field(&mut edit, field, value)?;
+        }
+
+        if edit == MetadataEdit::default() {
+            bail!("no fields given to set");
+        }
+
+        let paths: Vec<String> = args.cloned().collect();
+        Ok(Command::Set(edit, Self::paths(&paths)?))
+    }
+
+    fn parse_clear(args: &[String]) -> Result<Self> {
+        let mut edit = MetadataEdit::default();
+        let mut args = args.iter().peekable();
+
+        while args.peek().map(|a| a.as_str()) == Some("--field") {
+            args.next();
+            let field = args.next().context("missing name for --field")?;
+            clear_field(&mut edit, field)?;
+        }
+
+        if edit == MetadataEdit::default() {
+            bail!("no fields given to clear");
+        }
+
+        let paths: Vec<String> = args.cloned().collect();
+        Ok(Command::Clear(edit, Self::paths(&paths)?))
+    }
+
+    fn paths(args: &[String]) -> Result<Vec<PathBuf>> {
+        if let Some(option) = args.iter().find(|a| a.starts_with("--")) {
+            bail!("unexpected option {option}");
+        }
+        if args.is_empty() {
+            bail!("no files given");
+        }
+        Ok(args.iter().map(PathBuf::from).collect())
+    }
+}
+
+/// Runs a command, returning the process exit status. Files whose
+/// fields couldn't all be written, or read, make the status non-zero
+pub fn run(command: Result<Command>) -> i32 {
+    let command = match command {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("metanote-cli: {e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    let agent = MetadataAgent::new();
+    let mut failed = false;
+
+    match command {
+        Command::Help => println!("{USAGE}"),
+        Command::Get(paths) => {
+            for path in &paths {
+                match agent.metadata(path) {
+                    Ok(metadata) => print_metadata(path, &metadata, paths.len() > 1),
+                    Err(e) => {
+                        report_error(path, &e);
+                        failed = true;
+                    }
+                }
+            }
+        }
+        Command::Set(edit, paths) | Command::Clear(edit, paths) => {
+            for path in &paths {
+                if let Err(e) = apply_edit(&agent, path, &edit) {
+                    report_error(path, &e);
+                    failed = true;
+                }
+            }
+        }
+    }
+
+    i32::from(failed)
+}
+
+// Most errors name the file already
+fn report_error(path: &Path, e: &MetadataError) {
+    match e.path() {
+        Some(_) => eprintln!("metanote-cli: {e}"),
+        None => eprintln!("metanote-cli: {}: {e}", path.display()),
+    }
+}
+
+fn apply_edit<T>(agent: &T, path: &Path, edit: &MetadataEdit) -> Result<(), MetadataError>
+where
+    T: MetadataReadCapable + MetadataWriteCapable,
+{
+    let metadata = agent.metadata(path)?.apply(edit);
+    agent.write_metadata(path, &metadata)
+}
+
+fn print_metadata(path: &Path, metadata: &MetadataContainer, with_header: bool) {
+    if with_header {
+        println!("{}:", path.display());
+    }
+
+    for field in Field::MAIN {
+        if let Some(value) = field.get(metadata) {
+            println!("{}: {value}", field.name());
+        }
+    }
+    if let Some(art) = metadata.art() {
+        println!("art: {} image(s)", art.len());
+    }
+
+    for (field, value) in metadata.extra() {
+        println!("{}: {value}", field.name());
+    }
+
+    for (key, value) in metadata.custom() {
+        println!("{key}: {value}");
+    }
+}
+
+fn set_field(edit: &mut MetadataEdit, field: &str, value: &str) -> Result<()> {
+    match Field::from_name(field) {
+        Some(field) => field
+            .set(edit, FieldValue::Set(value.to_string()))
+            .map_err(Error::msg),
+        None => bail!("unknown field {field}"),
+    }
+}
+
+fn clear_field(edit: &mut MetadataEdit, field: &str) -> Result<()> {
+    if field == "art" {
+        edit.set_art(FieldValue::Cleared);
+        return Ok(());
+    }
+    match Field::from_name(field) {
+        Some(field) => field.set(edit, FieldValue::Cleared).map_err(Error::msg),
+        None => bail!("unknown field {field}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn unknown_commands_are_rejected() {
+        assert!(Command::parse(&[]).is_err());
+        assert!(Command::parse(&args(&["tag", "a.flac"])).is_err());
+        assert_eq!(Command::parse(&args(&["help"])).unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn set_parses_fields_and_files() {
+        let command =
+            Command::parse(&args(&["set", "--artist", "X", "--track", "3", "a.flac"])).unwrap();
+
+        let mut edit = MetadataEdit::default();
+        edit.set_artist(FieldValue::Set("X".to_string()));
+        edit.set_track_number(FieldValue::Set(3));
+        assert_eq!(command, Command::Set(edit, vec![PathBuf::from("a.flac")]));
+    }
+
+    #[test]
+    fn set_rejects_non_numeric_track() {
+        let command = Command::parse(&args(&["set", "--track", "A1", "a.flac"]));
+        assert!(command.is_err());
+    }
+
+    #[test]
+    fn unwritable_fields_fail_the_run() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("track.mp3");
+        let fixture =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../metanote-core/tests/fixtures/empty.mp3");
+        std::fs::copy(fixture, &path).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let command = Command::parse(&args(&["set", "--year", "1999", &path]));
+        assert_eq!(run(command), 0);
+        let command = Command::parse(&args(&["set", "--arranger", "X", &path]));
+        assert_eq!(run(command), 1);
+    }
+}
@@ -0,0 +1,308 @@
+// artwork.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use derive_builder::Builder;
+use getset::{CopyGetters, Setters};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageEncoder};
+
+use crate::error::{MetadataError, Result};
+use crate::metadata::Art;
+
+/// The quality images are saved at when they have to be
+/// saved as JPEG but no quality was chosen
+const DEFAULT_JPEG_QUALITY: u8 = 90;
+
+/// How images are reworked as they're embedded
+#[derive(Builder, Clone, Copy, Debug, Default, CopyGetters, PartialEq, Setters)]
+#[get_copy = "pub"]
+#[set = "pub"]
+pub struct ArtOptions {
+    /// Images wider or taller than this are scaled down
+    /// to fit, keeping their aspect ratio
+    #[builder(default)]
+    max_size: Option<u32>,
+    /// Recompresses JPEG images at this quality, from 1 to 100
+    #[builder(default)]
+    jpeg_quality: Option<u8>,
+    /// Saves PNG images without transparency as JPEG
+    #[builder(default)]
+    convert_png: bool,
+    /// Removes EXIF metadata, such as camera details and location
+    #[builder(default)]
+    strip_exif: bool,
+}
+
+impl ArtOptions {
+    /// Returns true if the options leave every image as it is
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Art {
+    /// Reworks the image as the options ask. The description and picture
+    /// type are kept, and the image is returned as it was if it needs no work
+    pub fn processed(&self, options: &ArtOptions) -> Result<Art> {
+        let is_jpeg = self.mime_type() == "image/jpeg";
+        let is_png = self.mime_type() == "image/png";
+        if options.is_empty() || !(is_jpeg || is_png || options.max_size().is_some()) {
+            return Ok(self.clone());
+        }
+
+        let image = image::load_from_memory(self.data()).map_err(invalid_image)?;
+        let too_large = options
+            .max_size()
+            .is_some_and(|max| image.width() > max || image.height() > max);
+        let convert = is_png && options.convert_png() && !has_transparency(&image);
+        let recompress = is_jpeg && options.jpeg_quality().is_some();
+
+        if !too_large && !convert && !recompress {
+            let mut art = self.clone();
+            if options.strip_exif() {
+                art.set_data(strip_exif(self.data()));
+            }
+            return Ok(art);
+        }
+
+        let image = match options.max_size() {
+            Some(max) if too_large => image.resize(max, max, FilterType::Lanczos3),
+            _ => image,
+        };
+
+        // Anything that isn't already a JPEG, or turning into one, is
+        // saved as PNG so nothing is lost beyond the resizing
+        let (mime_type, data) = if is_jpeg || convert {
+            let quality = options.jpeg_quality().unwrap_or(DEFAULT_JPEG_QUALITY);
+            ("image/jpeg", encode_jpeg(&image, quality)?)
+        } else {
+            ("image/png", encode_png(&image)?)
+        };
+
+        // Recompressing alone is no use if it makes the image larger
+        if !too_large && !convert && data.len() >= self.data().len() {
+            let mut art = self.clone();
+            if options.strip_exif() {
+                art.set_data(strip_exif(self.data()));
+            }
+            return Ok(art);
+        }
+
+        let mut art = self.clone();
+        art.set_mime_type(mime_type.to_string());
+        art.set_data(data);
+        Ok(art)
+    }
+}
+
+fn has_transparency(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX)
+}
+
+fn encode_jpeg(image: &DynamicImage, quality: u8) -> Result<Vec<u8>> {
+    let image = image.to_rgb8();
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, quality.clamp(1, 100))
+        .encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ColorType::Rgb8,
+        )
+        .map_err(invalid_image)?;
+    Ok(data)
+}
+
+fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let image = image.to_rgba8();
+    let mut data = Vec::new();
+    PngEncoder::new(&mut data)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )
+        .map_err(invalid_image)?;
+    Ok(data)
+}
+
+fn invalid_image(error: image::ImageError) -> MetadataError {
+    MetadataError::InvalidFieldValue {
+        field: "art".to_string(),
+        reason: error.to_string(),
+    }
+}
+
+/// Works out an image's MIME type from the magic bytes it starts
+/// with, returning `None` for data that isn't a known image format
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: [(&[u8], &str); 7] = [
+        (&[0xFF, 0xD8, 0xFF], "image/jpeg"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"BM", "image/bmp"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+    ];
+
+    if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    SIGNATURES
+        .into_iter()
+        .find(|(signature, _)| data.starts_with(signature))
+        .map(|(_, mime_type)| mime_type)
+}
+
+/// Removes EXIF metadata from JPEG or PNG data without re-encoding
+/// the image. Data in other formats, or that can't be followed,
+/// is returned as it was
+pub fn strip_exif(data: &[u8]) -> Vec<u8> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    let stripped = if data.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg_exif(data)
+    } else if data.starts_with(PNG_SIGNATURE) {
+        strip_png_exif(data, PNG_SIGNATURE.len())
+    } else {
+        None
+    };
+    stripped.unwrap_or_else(|| data.to_vec())
+}
+
+// Drops the APP1 segments holding EXIF, copying the rest,
+// including everything from the start of the image data
+fn strip_jpeg_exif(data: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = data[..2].to_vec();
+    let mut position = 2;
+
+    while position + 4 <= data.len() {
+        if data[position] != 0xFF {
+            return None;
+        }
+        let marker = data[position + 1];
+        // Start of scan, after which only image data follows
+        if marker == 0xDA {
+            stripped.extend_from_slice(&data[position..]);
+            return Some(stripped);
+        }
+
+        let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+        let end = position + 2 + length;
+        if length < 2 || end > data.len() {
+            return None;
+        }
+
+        let segment = &data[position..end];
+        if !(marker == 0xE1 && segment[4..].starts_with(b"Exif\0")) {
+            stripped.extend_from_slice(segment);
+        }
+        position = end;
+    }
+
+    None
+}
+
+// Drops any eXIf chunk, copying the other chunks as they are
+fn strip_png_exif(data: &[u8], signature_length: usize) -> Option<Vec<u8>> {
+    let mut stripped = data[..signature_length].to_vec();
+    let mut position = signature_length;
+
+    while position < data.len() {
+        let header = data.get(position..position + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        // Length, chunk type, data and CRC
+        let end = position + 12 + length;
+        let chunk = data.get(position..end)?;
+        if &header[4..8] != b"eXIf" {
+            stripped.extend_from_slice(chunk);
+        }
+        position = end;
+    }
+
+    Some(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::metadata::{ArtBuilder, PictureType};
+    use image::{Rgb, RgbImage};
+
+    fn jpeg_art(width: u32, height: u32) -> Art {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 0, 0])));
+        ArtBuilder::default()
+            .description(Some("Front".to_string()))
+            .pic_type(PictureType::CoverFront)
+            .mime_type("image/jpeg".to_string())
+            .data(encode_jpeg(&image, 100).unwrap())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn large_images_are_scaled_to_fit() {
+        let art = jpeg_art(400, 200);
+        let options = ArtOptionsBuilder::default()
+            .max_size(Some(100))
+            .build()
+            .unwrap();
+
+        let processed = art.processed(&options).unwrap();
+        let image = image::load_from_memory(processed.data()).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+        assert_eq!(processed.description(), art.description());
+        assert_eq!(processed.mime_type(), "image/jpeg");
+
+        let small = jpeg_art(50, 50);
+        assert_eq!(small.processed(&options).unwrap(), small);
+    }
+
+    #[test]
+    fn mime_type_comes_from_magic_bytes() {
+        assert_eq!(sniff_mime_type(jpeg_art(1, 1).data()), Some("image/jpeg"));
+        assert_eq!(
+            sniff_mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WAVEfmt "), None);
+        assert_eq!(sniff_mime_type(b"title=Song"), None);
+    }
+
+    #[test]
+    fn exif_is_stripped_from_jpeg() {
+        let art = jpeg_art(8, 8);
+        let exif = [
+            &[0xFF, 0xE1, 0x00, 0x0E][..],
+            b"Exif\0\0",
+            &[0, 0, 0, 0, 0, 0],
+        ]
+        .concat();
+        let with_exif = [&art.data()[..2], &exif[..], &art.data()[2..]].concat();
+
+        assert_eq!(strip_exif(&with_exif), art.data().clone());
+        assert_eq!(strip_exif(b"not an image"), b"not an image".to_vec());
+    }
+}
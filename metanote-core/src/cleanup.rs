@@ -0,0 +1,278 @@
+// cleanup.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use derive_builder::Builder;
+use getset::{CopyGetters, Getters};
+use unicode_normalization::UnicodeNormalization;
+
+/// Words title case leaves in lower case unless they start or end the text
+pub const DEFAULT_TITLE_CASE_EXCEPTIONS: &str =
+    "a an and as at but by feat. for from ft. in into nor of on or the to vs. with";
+
+/// The case text is converted to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextCase {
+    /// Every word capitalized, save for the exceptions
+    Title,
+    /// Only the first word of each sentence capitalized
+    Sentence,
+    Upper,
+    Lower,
+}
+
+/// How text is cleaned up
+#[derive(Builder, Clone, Debug, CopyGetters, Default, Getters, PartialEq)]
+pub struct CleanupOptions {
+    #[builder(default)]
+    #[get_copy = "pub"]
+    case: Option<TextCase>,
+    /// Words title case leaves in lower case, matched ignoring case
+    #[builder(default)]
+    #[get = "pub"]
+    title_case_exceptions: Vec<String>,
+    /// Leaves words written in capitals, such as ABBA, as they are in
+    /// title and sentence case. Words with digits or slashes, such as
+    /// U2 or AC/DC, are always left as they are
+    #[builder(default)]
+    #[get_copy = "pub"]
+    keep_capitals: bool,
+    /// Removes spaces from the start and end of the text
+    #[builder(default)]
+    #[get_copy = "pub"]
+    trim_whitespace: bool,
+    /// Turns each run of spaces and tabs into a single space
+    #[builder(default)]
+    #[get_copy = "pub"]
+    collapse_whitespace: bool,
+    /// Composes characters to Unicode Normalization Form C, so
+    /// the same text is always stored the same way
+    #[builder(default)]
+    #[get_copy = "pub"]
+    normalize: bool,
+    /// Removes control characters other than line breaks and tabs
+    #[builder(default)]
+    #[get_copy = "pub"]
+    remove_control_characters: bool,
+}
+
+/// Splits a list of words, such as `DEFAULT_TITLE_CASE_EXCEPTIONS`, on whitespace
+pub fn parse_exceptions(words: &str) -> Vec<String> {
+    words.split_whitespace().map(str::to_lowercase).collect()
+}
+
+/// Cleans up the text as the options ask
+pub fn clean_text(text: &str, options: &CleanupOptions) -> String {
+    let mut text = if options.normalize() {
+        text.nfc().collect()
+    } else {
+        text.to_string()
+    };
+
+    if options.remove_control_characters() {
+        text.retain(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'));
+    }
+    if options.collapse_whitespace() {
+        text = collapse_whitespace(&text);
+    }
+    if options.trim_whitespace() {
+        text = text.trim().to_string();
+    }
+
+    match options.case() {
+        Some(TextCase::Title) => title_case(&text, options),
+        Some(TextCase::Sentence) => sentence_case(&text, options),
+        Some(TextCase::Upper) => text.to_uppercase(),
+        Some(TextCase::Lower) => text.to_lowercase(),
+        None => text,
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_run = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\n' && c != '\r' {
+            if !in_run {
+                collapsed.push(' ');
+            }
+            in_run = true;
+        } else {
+            collapsed.push(c);
+            in_run = false;
+        }
+    }
+    collapsed
+}
+
+fn title_case(text: &str, options: &CleanupOptions) -> String {
+    let words: Vec<&str> = text.split(' ').collect();
+    let last = words.iter().rposition(|word| !word.is_empty()).unwrap_or(0);
+    let first = words.iter().position(|word| !word.is_empty()).unwrap_or(0);
+
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| {
+            let bare = word
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .trim_end_matches(|c: char| !c.is_alphanumeric() && c != '.')
+                .to_lowercase();
+            if keeps_case(word, options) {
+                word.to_string()
+            } else if i != first && i != last && options.title_case_exceptions().contains(&bare) {
+                word.to_lowercase()
+            } else {
+                capitalize(word)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn sentence_case(text: &str, options: &CleanupOptions) -> String {
+    let mut sentence_start = true;
+    text.split(' ')
+        .map(|word| {
+            let converted = if keeps_case(word, options) {
+                word.to_string()
+            } else if sentence_start {
+                capitalize(word)
+            } else if is_mixed_case(word) {
+                word.to_string()
+            } else {
+                word.to_lowercase()
+            };
+            if !word.is_empty() {
+                sentence_start = word.ends_with(['.', '!', '?']);
+            }
+            converted
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// Words whose case is part of how they're written
+fn keeps_case(word: &str, options: &CleanupOptions) -> bool {
+    if word.contains(|c: char| c == '/' || c.is_ascii_digit()) {
+        return true;
+    }
+    let mut letters = word.chars().filter(|c| c.is_alphabetic()).peekable();
+    options.keep_capitals() && letters.peek().is_some() && letters.all(char::is_uppercase)
+}
+
+// Upper cases the word's first letter. Words that mix cases on purpose,
+// such as McCartney or iTunes, keep the rest of their letters as they are,
+// while the rest of any other word is lower cased
+fn capitalize(word: &str) -> String {
+    let keep_rest = is_mixed_case(word);
+    let mut capitalized = String::with_capacity(word.len());
+    let mut found_letter = false;
+    for c in word.chars() {
+        if !found_letter && c.is_alphabetic() {
+            capitalized.extend(c.to_uppercase());
+            found_letter = true;
+        } else if keep_rest {
+            capitalized.push(c);
+        } else {
+            capitalized.extend(c.to_lowercase());
+        }
+    }
+    capitalized
+}
+
+// Words with a capital after their first letter, that aren't all capitals
+fn is_mixed_case(word: &str) -> bool {
+    let mut letters = word.chars().filter(|c| c.is_alphabetic());
+    letters.next();
+    let rest: Vec<char> = letters.collect();
+    rest.iter().any(|c| c.is_uppercase()) && rest.iter().any(|c| c.is_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn options(case: Option<TextCase>) -> CleanupOptions {
+        CleanupOptionsBuilder::default()
+            .case(case)
+            .title_case_exceptions(parse_exceptions(DEFAULT_TITLE_CASE_EXCEPTIONS))
+            .trim_whitespace(true)
+            .collapse_whitespace(true)
+            .normalize(true)
+            .remove_control_characters(true)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn title_case_leaves_exceptions_lower() {
+        let options = options(Some(TextCase::Title));
+        assert_eq!(
+            clean_text("the LORD of the rings (FEAT. someone)", &options),
+            "The Lord of the Rings (feat. Someone)"
+        );
+        assert_eq!(
+            clean_text("songs by McCartney to sing along to", &options),
+            "Songs by McCartney to Sing Along To"
+        );
+    }
+
+    #[test]
+    fn acronyms_keep_their_case() {
+        let mut options = options(Some(TextCase::Title));
+        assert_eq!(clean_text("AC/DC", &options), "AC/DC");
+        assert_eq!(clean_text("U2 LIVE", &options), "U2 Live");
+        assert_eq!(clean_text("ABBA gold", &options), "Abba Gold");
+
+        options.keep_capitals = true;
+        assert_eq!(clean_text("U2 LIVE", &options), "U2 LIVE");
+        assert_eq!(clean_text("ABBA gold", &options), "ABBA Gold");
+
+        options.case = Some(TextCase::Sentence);
+        assert_eq!(
+            clean_text("best of ABBA. live IN ac/dc", &options),
+            "Best of ABBA. Live IN ac/dc"
+        );
+    }
+
+    #[test]
+    fn sentence_case_capitalizes_each_sentence() {
+        assert_eq!(
+            clean_text(
+                "HELLO THERE. how ARE you? fine",
+                &options(Some(TextCase::Sentence))
+            ),
+            "Hello there. How are you? Fine"
+        );
+    }
+
+    #[test]
+    fn whitespace_and_control_characters_are_cleaned() {
+        assert_eq!(
+            clean_text("  A \t  Song\u{0}\u{7}  ", &options(None)),
+            "A Song"
+        );
+        assert_eq!(clean_text("Cafe\u{301}", &options(None)), "Caf\u{e9}");
+        assert_eq!(
+            clean_text("line one\nline two", &options(Some(TextCase::Upper))),
+            "LINE ONE\nLINE TWO"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// error.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use lofty::error::ErrorKind;
+use lofty::LoftyError;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, MetadataError>;
+
+/// The ways reading or writing a file's metadata can fail
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("{} is not a supported audio format", .0.display())]
+    UnsupportedFormat(PathBuf),
+
+    #[error("{} has no tag to write to", .0.display())]
+    NoTag(PathBuf),
+
+    #[error("{} can't hold a {tag_type} tag", .path.display())]
+    UnsupportedTagType { path: PathBuf, tag_type: String },
+
+    #[error("{} is not an image in a format that can be embedded", .0.display())]
+    NotAnImage(PathBuf),
+
+    #[error("{} is read-only", .0.display())]
+    ReadOnly(PathBuf),
+
+    #[error("the tag in {} is corrupted, {reason}", .path.display())]
+    CorruptedTag { path: PathBuf, reason: String },
+
+    #[error("{}: {field} can't be stored in {tag_type} tags", .path.display())]
+    UnsupportedField {
+        path: PathBuf,
+        field: String,
+        tag_type: String,
+    },
+
+    #[error("invalid value for {field}, {reason}")]
+    InvalidFieldValue { field: String, reason: String },
+
+    #[error("{}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+}
+
+impl MetadataError {
+    /// Classifies an error from lofty raised while handling the file at `path`
+    pub fn from_lofty(path: &Path, error: LoftyError) -> Self {
+        match error.kind() {
+            ErrorKind::UnknownFormat => MetadataError::UnsupportedFormat(path.to_path_buf()),
+            // lofty only lends the I/O error out, so it's rebuilt from its kind and message
+            ErrorKind::Io(source) => {
+                MetadataError::from_io(path, io::Error::new(source.kind(), source.to_string()))
+            }
+            _ => MetadataError::CorruptedTag {
+                path: path.to_path_buf(),
+                reason: error.to_string(),
+            },
+        }
+    }
+
+    /// Classifies an I/O error raised while handling the file at `path`
+    pub fn from_io(path: &Path, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => MetadataError::ReadOnly(path.to_path_buf()),
+            _ => MetadataError::Io {
+                path: path.to_path_buf(),
+                source,
+            },
+        }
+    }
+
+    /// Returns the file the error concerns, if it concerns one
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            MetadataError::UnsupportedFormat(path)
+            | MetadataError::NoTag(path)
+            | MetadataError::NotAnImage(path)
+            | MetadataError::ReadOnly(path)
+            | MetadataError::UnsupportedTagType { path, .. }
+            | MetadataError::UnsupportedField { path, .. }
+            | MetadataError::CorruptedTag { path, .. }
+            | MetadataError::Io { path, .. } => Some(path),
+            MetadataError::InvalidFieldValue { .. } => None,
+        }
+    }
+}
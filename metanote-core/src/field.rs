@@ -0,0 +1,462 @@
+// field.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use lofty::{ItemKey, TagType};
+
+use crate::metadata::tag_holds;
+
+use crate::metadata::{FieldValue, MetadataContainer, MetadataEdit};
+
+/// Any text or number field of a track, looked up by the same name
+/// on the command line, in rename templates and in file name patterns
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Field {
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    TrackNumber,
+    TrackTotal,
+    Genre,
+    Year,
+    DiscNumber,
+    DiscTotal,
+    Composer,
+    Comment,
+    Copyright,
+    Extra(ExtraField),
+}
+
+impl Field {
+    /// The fields `MetadataContainer` names outright
+    pub const MAIN: [Field; 13] = [
+        Field::Title,
+        Field::Artist,
+        Field::AlbumArtist,
+        Field::Album,
+        Field::TrackNumber,
+        Field::TrackTotal,
+        Field::Genre,
+        Field::Year,
+        Field::DiscNumber,
+        Field::DiscTotal,
+        Field::Composer,
+        Field::Comment,
+        Field::Copyright,
+    ];
+
+    /// The text fields among `MAIN`, in the order the editor shows them
+    pub const TEXT: [Field; 9] = [
+        Field::Title,
+        Field::Artist,
+        Field::AlbumArtist,
+        Field::Album,
+        Field::Genre,
+        Field::Year,
+        Field::Composer,
+        Field::Comment,
+        Field::Copyright,
+    ];
+
+    /// The field's name on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Artist => "artist",
+            Field::AlbumArtist => "album-artist",
+            Field::Album => "album",
+            Field::TrackNumber => "track",
+            Field::TrackTotal => "track-total",
+            Field::Genre => "genre",
+            Field::Year => "year",
+            Field::DiscNumber => "disc",
+            Field::DiscTotal => "disc-total",
+            Field::Composer => "composer",
+            Field::Comment => "comment",
+            Field::Copyright => "copyright",
+            Field::Extra(field) => field.name(),
+        }
+    }
+
+    /// The field's name as shown in the editor
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Title => "Title",
+            Field::Artist => "Artist",
+            Field::AlbumArtist => "Album Artist",
+            Field::Album => "Album",
+            Field::TrackNumber => "Track",
+            Field::TrackTotal => "Track Total",
+            Field::Genre => "Genre",
+            Field::Year => "Year",
+            Field::DiscNumber => "Disc",
+            Field::DiscTotal => "Disc Total",
+            Field::Composer => "Composer",
+            Field::Comment => "Comment",
+            Field::Copyright => "Copyright",
+            Field::Extra(field) => field.label(),
+        }
+    }
+
+    /// Looks a field up by its command line name, where
+    /// templates and patterns may write `_` for `-`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.replace('_', "-");
+        Self::MAIN
+            .into_iter()
+            .find(|field| field.name() == name)
+            .or_else(|| ExtraField::from_name(&name).map(Field::Extra))
+    }
+
+    pub fn is_number(self) -> bool {
+        matches!(
+            self,
+            Field::TrackNumber | Field::TrackTotal | Field::DiscNumber | Field::DiscTotal
+        )
+    }
+
+    /// The field's value as text, if the track has one
+    pub fn get(self, metadata: &MetadataContainer) -> Option<String> {
+        let number = |n: &Option<i32>| n.map(|n| n.to_string());
+        match self {
+            Field::Title => metadata.title().clone(),
+            Field::Artist => metadata.artist().clone(),
+            Field::AlbumArtist => metadata.album_artist().clone(),
+            Field::Album => metadata.album().clone(),
+            Field::TrackNumber => number(metadata.track_number()),
+            Field::TrackTotal => number(metadata.track_total()),
+            Field::Genre => metadata.genre().clone(),
+            Field::Year => metadata.year().clone(),
+            Field::DiscNumber => number(metadata.disc_number()),
+            Field::DiscTotal => number(metadata.disc_total()),
+            Field::Composer => metadata.composer().clone(),
+            Field::Comment => metadata.comment().clone(),
+            Field::Copyright => metadata.copyright().clone(),
+            Field::Extra(field) => metadata.extra().get(&field).cloned(),
+        }
+    }
+
+    /// Stages a change to the field, failing if a number field is set to text
+    pub fn set(self, edit: &mut MetadataEdit, value: FieldValue<String>) -> Result<(), String> {
+        let parse = |text: &str| {
+            text.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("{} must be a number, not \"{text}\"", self.name()))
+        };
+        let number = || match &value {
+            FieldValue::Unchanged => Ok(FieldValue::Unchanged),
+            FieldValue::Mixed(values) => values
+                .iter()
+                .map(|value| value.as_deref().map(parse).transpose())
+                .collect::<Result<_, _>>()
+                .map(FieldValue::Mixed),
+            FieldValue::Set(text) => parse(text).map(FieldValue::Set),
+            FieldValue::Cleared => Ok(FieldValue::Cleared),
+        };
+
+        match self {
+            Field::Title => edit.set_title(value),
+            Field::Artist => edit.set_artist(value),
+            Field::AlbumArtist => edit.set_album_artist(value),
+            Field::Album => edit.set_album(value),
+            Field::TrackNumber => edit.set_track_number(number()?),
+            Field::TrackTotal => edit.set_track_total(number()?),
+            Field::Genre => edit.set_genre(value),
+            Field::Year => edit.set_year(value),
+            Field::DiscNumber => edit.set_disc_number(number()?),
+            Field::DiscTotal => edit.set_disc_total(number()?),
+            Field::Composer => edit.set_composer(value),
+            Field::Comment => edit.set_comment(value),
+            Field::Copyright => edit.set_copyright(value),
+            Field::Extra(field) => {
+                edit.extra_mut().insert(field, value);
+                edit
+            }
+        };
+        Ok(())
+    }
+}
+
+/// A standard text field beyond the ones `MetadataContainer` names
+/// outright, each mapped to one of lofty's generic keys
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ExtraField {
+    Subtitle,
+    DiscSubtitle,
+    Grouping,
+    OriginalAlbum,
+    OriginalArtist,
+    OriginalLyricist,
+    TitleSortOrder,
+    ArtistSortOrder,
+    AlbumSortOrder,
+    AlbumArtistSortOrder,
+    ComposerSortOrder,
+    Lyricist,
+    Writer,
+    Arranger,
+    Conductor,
+    Performer,
+    Producer,
+    Engineer,
+    MixEngineer,
+    MixDj,
+    Remixer,
+    Publisher,
+    Label,
+    CatalogNumber,
+    Isrc,
+    Barcode,
+    OriginalReleaseDate,
+    Bpm,
+    InitialKey,
+    Mood,
+    Language,
+    Script,
+    Description,
+    Lyrics,
+    License,
+    EncodedBy,
+    EncoderSoftware,
+    EncoderSettings,
+}
+
+impl ExtraField {
+    /// Every extra field, in the order the editor shows them
+    pub const ALL: [ExtraField; 38] = [
+        ExtraField::Subtitle,
+        ExtraField::DiscSubtitle,
+        ExtraField::Grouping,
+        ExtraField::OriginalAlbum,
+        ExtraField::OriginalArtist,
+        ExtraField::OriginalLyricist,
+        ExtraField::TitleSortOrder,
+        ExtraField::ArtistSortOrder,
+        ExtraField::AlbumSortOrder,
+        ExtraField::AlbumArtistSortOrder,
+        ExtraField::ComposerSortOrder,
+        ExtraField::Lyricist,
+        ExtraField::Writer,
+        ExtraField::Arranger,
+        ExtraField::Conductor,
+        ExtraField::Performer,
+        ExtraField::Producer,
+        ExtraField::Engineer,
+        ExtraField::MixEngineer,
+        ExtraField::MixDj,
+        ExtraField::Remixer,
+        ExtraField::Publisher,
+        ExtraField::Label,
+        ExtraField::CatalogNumber,
+        ExtraField::Isrc,
+        ExtraField::Barcode,
+        ExtraField::OriginalReleaseDate,
+        ExtraField::Bpm,
+        ExtraField::InitialKey,
+        ExtraField::Mood,
+        ExtraField::Language,
+        ExtraField::Script,
+        ExtraField::Description,
+        ExtraField::Lyrics,
+        ExtraField::License,
+        ExtraField::EncodedBy,
+        ExtraField::EncoderSoftware,
+        ExtraField::EncoderSettings,
+    ];
+
+    /// The key lofty stores the field under
+    pub fn item_key(self) -> ItemKey {
+        match self {
+            ExtraField::Subtitle => ItemKey::TrackSubtitle,
+            ExtraField::DiscSubtitle => ItemKey::SetSubtitle,
+            ExtraField::Grouping => ItemKey::ContentGroup,
+            ExtraField::OriginalAlbum => ItemKey::OriginalAlbumTitle,
+            ExtraField::OriginalArtist => ItemKey::OriginalArtist,
+            ExtraField::OriginalLyricist => ItemKey::OriginalLyricist,
+            ExtraField::TitleSortOrder => ItemKey::TrackTitleSortOrder,
+            ExtraField::ArtistSortOrder => ItemKey::TrackArtistSortOrder,
+            ExtraField::AlbumSortOrder => ItemKey::AlbumTitleSortOrder,
+            ExtraField::AlbumArtistSortOrder => ItemKey::AlbumArtistSortOrder,
+            ExtraField::ComposerSortOrder => ItemKey::ComposerSortOrder,
+            ExtraField::Lyricist => ItemKey::Lyricist,
+            ExtraField::Writer => ItemKey::Writer,
+            ExtraField::Arranger => ItemKey::Arranger,
+            ExtraField::Conductor => ItemKey::Conductor,
+            ExtraField::Performer => ItemKey::Performer,
+            ExtraField::Producer => ItemKey::Producer,
+            ExtraField::Engineer => ItemKey::Engineer,
+            ExtraField::MixEngineer => ItemKey::MixEngineer,
+            ExtraField::MixDj => ItemKey::MixDj,
+            ExtraField::Remixer => ItemKey::Remixer,
+            ExtraField::Publisher => ItemKey::Publisher,
+            ExtraField::Label => ItemKey::Label,
+            ExtraField::CatalogNumber => ItemKey::CatalogNumber,
+            ExtraField::Isrc => ItemKey::ISRC,
+            ExtraField::Barcode => ItemKey::Barcode,
+            ExtraField::OriginalReleaseDate => ItemKey::OriginalReleaseDate,
+            ExtraField::Bpm => ItemKey::BPM,
+            ExtraField::InitialKey => ItemKey::InitialKey,
+            ExtraField::Mood => ItemKey::Mood,
+            ExtraField::Language => ItemKey::Language,
+            ExtraField::Script => ItemKey::Script,
+            ExtraField::Description => ItemKey::Description,
+            ExtraField::Lyrics => ItemKey::Lyrics,
+            ExtraField::License => ItemKey::License,
+            ExtraField::EncodedBy => ItemKey::EncodedBy,
+            ExtraField::EncoderSoftware => ItemKey::EncoderSoftware,
+            ExtraField::EncoderSettings => ItemKey::EncoderSettings,
+        }
+    }
+
+    /// The field's name on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            ExtraField::Subtitle => "subtitle",
+            ExtraField::DiscSubtitle => "disc-subtitle",
+            ExtraField::Grouping => "grouping",
+            ExtraField::OriginalAlbum => "original-album",
+            ExtraField::OriginalArtist => "original-artist",
+            ExtraField::OriginalLyricist => "original-lyricist",
+            ExtraField::TitleSortOrder => "title-sort",
+            ExtraField::ArtistSortOrder => "artist-sort",
+            ExtraField::AlbumSortOrder => "album-sort",
+            ExtraField::AlbumArtistSortOrder => "album-artist-sort",
+            ExtraField::ComposerSortOrder => "composer-sort",
+            ExtraField::Lyricist => "lyricist",
+            ExtraField::Writer => "writer",
+            ExtraField::Arranger => "arranger",
+            ExtraField::Conductor => "conductor",
+            ExtraField::Performer => "performer",
+            ExtraField::Producer => "producer",
+            ExtraField::Engineer => "engineer",
+            ExtraField::MixEngineer => "mix-engineer",
+            ExtraField::MixDj => "mix-dj",
+            ExtraField::Remixer => "remixer",
+            ExtraField::Publisher => "publisher",
+            ExtraField::Label => "label",
+            ExtraField::CatalogNumber => "catalog-number",
+            ExtraField::Isrc => "isrc",
+            ExtraField::Barcode => "barcode",
+            ExtraField::OriginalReleaseDate => "original-date",
+            ExtraField::Bpm => "bpm",
+            ExtraField::InitialKey => "key",
+            ExtraField::Mood => "mood",
+            ExtraField::Language => "language",
+            ExtraField::Script => "script",
+            ExtraField::Description => "description",
+            ExtraField::Lyrics => "lyrics",
+            ExtraField::License => "license",
+            ExtraField::EncodedBy => "encoded-by",
+            ExtraField::EncoderSoftware => "encoder",
+            ExtraField::EncoderSettings => "encoder-settings",
+        }
+    }
+
+    /// The field's name as shown in the editor
+    pub fn label(self) -> &'static str {
+        match self {
+            ExtraField::Subtitle => "Subtitle",
+            ExtraField::DiscSubtitle => "Disc Subtitle",
+            ExtraField::Grouping => "Grouping",
+            ExtraField::OriginalAlbum => "Original Album",
+            ExtraField::OriginalArtist => "Original Artist",
+            ExtraField::OriginalLyricist => "Original Lyricist",
+            ExtraField::TitleSortOrder => "Title Sort Order",
+            ExtraField::ArtistSortOrder => "Artist Sort Order",
+            ExtraField::AlbumSortOrder => "Album Sort Order",
+            ExtraField::AlbumArtistSortOrder => "Album Artist Sort Order",
+            ExtraField::ComposerSortOrder => "Composer Sort Order",
+            ExtraField::Lyricist => "Lyricist",
+            ExtraField::Writer => "Writer",
+            ExtraField::Arranger => "Arranger",
+            ExtraField::Conductor => "Conductor",
+            ExtraField::Performer => "Performer",
+            ExtraField::Producer => "Producer",
+            ExtraField::Engineer => "Engineer",
+            ExtraField::MixEngineer => "Mix Engineer",
+            ExtraField::MixDj => "Mix DJ",
+            ExtraField::Remixer => "Remixer",
+            ExtraField::Publisher => "Publisher",
+            ExtraField::Label => "Label",
+            ExtraField::CatalogNumber => "Catalog Number",
+            ExtraField::Isrc => "ISRC",
+            ExtraField::Barcode => "Barcode",
+            ExtraField::OriginalReleaseDate => "Original Release Date",
+            ExtraField::Bpm => "BPM",
+            ExtraField::InitialKey => "Key",
+            ExtraField::Mood => "Mood",
+            ExtraField::Language => "Language",
+            ExtraField::Script => "Script",
+            ExtraField::Description => "Description",
+            ExtraField::Lyrics => "Lyrics",
+            ExtraField::License => "License",
+            ExtraField::EncodedBy => "Encoded By",
+            ExtraField::EncoderSoftware => "Encoder",
+            ExtraField::EncoderSettings => "Encoder Settings",
+        }
+    }
+
+    /// Looks a field up by its command line name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field| field.name() == name)
+    }
+
+    /// Whether tags of the given type have an item of their own for the field
+    pub fn held_by(self, tag_type: TagType) -> bool {
+        tag_holds(tag_type, &self.item_key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn names_are_unique_and_round_trip() {
+        for field in ExtraField::ALL {
+            assert_eq!(ExtraField::from_name(field.name()), Some(field));
+        }
+        assert_eq!(ExtraField::from_name("title"), None);
+    }
+
+    #[test]
+    fn fields_are_found_by_name_and_set() {
+        assert_eq!(Field::from_name("album_artist"), Some(Field::AlbumArtist));
+        assert_eq!(Field::from_name("track-total"), Some(Field::TrackTotal));
+        assert_eq!(
+            Field::from_name("catalog_number"),
+            Some(Field::Extra(ExtraField::CatalogNumber))
+        );
+        assert_eq!(Field::from_name("nope"), None);
+
+        let mut edit = MetadataEdit::default();
+        Field::TrackNumber
+            .set(&mut edit, FieldValue::Set(" 03".to_string()))
+            .unwrap();
+        assert_eq!(edit.track_number(), &FieldValue::Set(3));
+        assert!(Field::DiscNumber
+            .set(&mut edit, FieldValue::Set("A".to_string()))
+            .is_err());
+
+        let metadata = MetadataContainer::default().apply(&edit);
+        assert_eq!(Field::TrackNumber.get(&metadata), Some("3".to_string()));
+        assert_eq!(Field::Title.get(&metadata), None);
+    }
+}
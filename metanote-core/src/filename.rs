@@ -0,0 +1,199 @@
+// filename.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::path::Path;
+
+use crate::field::Field;
+use crate::metadata::{FieldValue, MetadataEdit};
+
+/// The pattern tags are read from file names with unless another is given
+pub const DEFAULT_FILENAME_PATTERN: &str = "{track} - {artist} - {title}";
+
+/// The field name that matches text without keeping it
+pub const IGNORED_FIELD: &str = "_";
+
+enum Token {
+    Literal(String),
+    Field(String),
+}
+
+/// Reads fields out of a track's path with a pattern such as
+/// `{track} - {artist} - {title}`. The extension is left out, and
+/// each `/` in the pattern matches one more of the parent folders,
+/// as in `{artist}/{album}/{track} {title}`. Fields are returned in
+/// the order they're found, or an error if the path doesn't match
+pub fn parse_path(pattern: &str, path: &Path) -> Result<Vec<(String, String)>, String> {
+    let tokens = tokenize(pattern)?;
+
+    let depth = pattern.matches('/').count() + 1;
+    let components: Vec<String> = path
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if components.len() < depth {
+        return Err("the path has fewer folders than the pattern".to_string());
+    }
+    let text = components[components.len() - depth..].join("/");
+
+    let mut fields = Vec::new();
+    if !match_tokens(&tokens, &text, &mut fields) {
+        return Err("doesn't match the pattern".to_string());
+    }
+    fields.retain(|(name, _)| name != IGNORED_FIELD);
+    Ok(fields)
+}
+
+/// Turns fields read by `parse_path` into an edit that sets them,
+/// leaving every other field as it is
+pub fn edit_from_fields(fields: &[(String, String)]) -> Result<MetadataEdit, String> {
+    let mut edit = MetadataEdit::default();
+
+    for (name, value) in fields {
+        let field =
+            Field::from_name(name).ok_or_else(|| format!("there's no field called {name}"))?;
+        field.set(&mut edit, FieldValue::Set(value.trim().to_string()))?;
+    }
+
+    Ok(edit)
+}
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(format!("{{{name} is never closed"));
+                }
+                if name.is_empty() {
+                    return Err("{} needs a field name".to_string());
+                }
+
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                tokens.push(Token::Field(name));
+            }
+            '}' => return Err("there's a } without a {".to_string()),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+
+    Ok(tokens)
+}
+
+// Matches the whole text, trying the shortest value for each field
+// first. Values never reach across folders
+fn match_tokens(tokens: &[Token], text: &str, fields: &mut Vec<(String, String)>) -> bool {
+    match tokens.split_first() {
+        None => text.is_empty(),
+        Some((Token::Literal(literal), rest)) => match text.strip_prefix(literal.as_str()) {
+            Some(text) => match_tokens(rest, text, fields),
+            None => false,
+        },
+        Some((Token::Field(name), rest)) => {
+            let ends = text
+                .char_indices()
+                .map(|(i, _)| i)
+                .skip(1)
+                .chain(std::iter::once(text.len()));
+            for end in ends {
+                let value = &text[..end];
+                if value.contains('/') {
+                    break;
+                }
+
+                fields.push((name.clone(), value.to_string()));
+                if match_tokens(rest, &text[end..], fields) {
+                    return true;
+                }
+                fields.pop();
+            }
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fields_are_read_from_file_names() {
+        let path = Path::new("/music/03 - Artist - A Title - Live.mp3");
+        assert_eq!(
+            parse_path(DEFAULT_FILENAME_PATTERN, path),
+            Ok(fields(&[
+                ("track", "03"),
+                ("artist", "Artist"),
+                ("title", "A Title - Live")
+            ]))
+        );
+        assert!(parse_path("{track}. {title}", path).is_err());
+    }
+
+    #[test]
+    fn folders_can_be_matched() {
+        let path = Path::new("/music/The Band/1999 - Album/01 Song.flac");
+        assert_eq!(
+            parse_path("{artist}/{year} - {album}/{_} {title}", path),
+            Ok(fields(&[
+                ("artist", "The Band"),
+                ("year", "1999"),
+                ("album", "Album"),
+                ("title", "Song")
+            ]))
+        );
+    }
+
+    #[test]
+    fn edit_sets_only_the_fields_found() {
+        let edit = edit_from_fields(&fields(&[("track", "03"), ("title", " Song ")])).unwrap();
+        assert_eq!(edit.track_number(), &FieldValue::Set(3));
+        assert_eq!(edit.title(), &FieldValue::Set("Song".to_string()));
+        assert_eq!(edit.artist(), &FieldValue::Unchanged);
+
+        assert!(edit_from_fields(&fields(&[("track", "three")])).is_err());
+        assert!(edit_from_fields(&fields(&[("nope", "x")])).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+// lib.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The tag engine behind Metanote. Reads and writes audio metadata
+//! through lofty without depending on GTK, so it can be used headless.
+
+pub mod artwork;
+pub mod cleanup;
+pub mod error;
+pub mod field;
+pub mod filename;
+pub mod metadata;
+pub mod numbering;
+pub mod position;
+pub mod rename;
+pub mod replace;
+pub mod scan;
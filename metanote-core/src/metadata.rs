@@ -0,0 +1,1481 @@
+// metadata.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use derive_builder::Builder;
+use getset::{Getters, MutGetters, Setters};
+use lofty::id3::v2::{EncodedTextFrame, Frame, FrameFlags, FrameValue, Id3v2Tag, TextEncoding};
+use lofty::iff::{AiffFile, WavFile};
+use lofty::mp3::Mp3File;
+use lofty::{Accessor, AudioFile, FileType, ItemKey, ItemValue, Probe, Tag, TagExt, TagItem};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::artwork::sniff_mime_type;
+use crate::error::{MetadataError, Result};
+use crate::field::ExtraField;
+use crate::position::{MetadataWarning, Position};
+
+pub use lofty::{PictureType, TagType};
+
+#[derive(Builder, Clone, Debug, Default, Getters, PartialEq, Setters)]
+#[get = "pub"]
+#[set = "pub"]
+#[allow(dead_code)]
+pub struct MetadataContainer {
+    title: Option<String>,
+    artist: Option<String>,
+    album_artist: Option<String>,
+    album: Option<String>,
+    track_number: Option<i32>,
+    track_total: Option<i32>,
+    genre: Option<String>,
+    year: Option<String>,
+    disc_number: Option<i32>,
+    disc_total: Option<i32>,
+    composer: Option<String>,
+    comment: Option<String>,
+    copyright: Option<String>,
+    art: Option<Vec<Art>>,
+    /// Extra fields the track has a value for
+    #[builder(default)]
+    extra: BTreeMap<ExtraField, String>,
+    /// Text items no field covers, such as custom Vorbis comments,
+    /// keyed as the tag format names them. ID3v2 user text frames
+    /// are keyed as `TXXX:` followed by their description
+    #[builder(default)]
+    custom: BTreeMap<String, String>,
+    /// The tag the fields were read from
+    #[builder(default)]
+    tag_type: Option<TagType>,
+    /// Every tag the file holds
+    #[builder(default)]
+    tag_types: Vec<TagType>,
+    /// Every kind of tag the file's format can hold
+    #[builder(default)]
+    supported_tag_types: Vec<TagType>,
+    /// The tags a save writes the fields to. The file's
+    /// primary tag is used when this is empty
+    #[builder(default)]
+    write_to: Vec<TagType>,
+    #[builder(default)]
+    warnings: Vec<MetadataWarning>,
+}
+
+impl MetadataContainer {
+    /// Returns a single MetadataEdit consolidated from the given containers.
+    /// Fields that agree become `Set` or `Cleared`, fields that differ
+    /// become `Mixed` with each distinct value listed once
+    pub fn merge(containers: &[Self]) -> MetadataEdit {
+        if containers.is_empty() {
+            return MetadataEdit::default();
+        }
+
+        MetadataEditBuilder::default()
+            .title(FieldValue::from_values(
+                containers.iter().map(|c| c.title()),
+            ))
+            .artist(FieldValue::from_values(
+                containers.iter().map(|c| c.artist()),
+            ))
+            .album_artist(FieldValue::from_values(
+                containers.iter().map(|c| c.album_artist()),
+            ))
+            .album(FieldValue::from_values(
+                containers.iter().map(|c| c.album()),
+            ))
+            .track_number(FieldValue::from_values(
+                containers.iter().map(|c| c.track_number()),
+            ))
+            .track_total(FieldValue::from_values(
+                containers.iter().map(|c| c.track_total()),
+            ))
+            .genre(FieldValue::from_values(
+                containers.iter().map(|c| c.genre()),
+            ))
+            .year(FieldValue::from_values(containers.iter().map(|c| c.year())))
+            .disc_number(FieldValue::from_values(
+                containers.iter().map(|c| c.disc_number()),
+            ))
+            .disc_total(FieldValue::from_values(
+                containers.iter().map(|c| c.disc_total()),
+            ))
+            .composer(FieldValue::from_values(
+                containers.iter().map(|c| c.composer()),
+            ))
+            .comment(FieldValue::from_values(
+                containers.iter().map(|c| c.comment()),
+            ))
+            .copyright(FieldValue::from_values(
+                containers.iter().map(|c| c.copyright()),
+            ))
+            .art(FieldValue::from_values(containers.iter().map(|c| c.art())))
+            .extra(
+                ExtraField::ALL
+                    .into_iter()
+                    .map(|field| {
+                        let values: Vec<Option<String>> = containers
+                            .iter()
+                            .map(|c| c.extra().get(&field).cloned())
+                            .collect();
+                        (field, FieldValue::from_values(&values))
+                    })
+                    .collect(),
+            )
+            .custom(
+                containers
+                    .iter()
+                    .flat_map(|c| c.custom().keys())
+                    .map(|key| {
+                        let values: Vec<Option<String>> = containers
+                            .iter()
+                            .map(|c| c.custom().get(key).cloned())
+                            .collect();
+                        (key.clone(), FieldValue::from_values(&values))
+                    })
+                    .collect(),
+            )
+            .build()
+            .expect("failed to build consolidated metadata")
+    }
+
+    /// Returns a copy of this container with the edit applied.
+    /// `Unchanged` and `Mixed` fields keep this container's value
+    pub fn apply(&self, edit: &MetadataEdit) -> Self {
+        Self {
+            title: edit.title().apply(self.title()),
+            artist: edit.artist().apply(self.artist()),
+            album_artist: edit.album_artist().apply(self.album_artist()),
+            album: edit.album().apply(self.album()),
+            track_number: edit.track_number().apply(self.track_number()),
+            track_total: edit.track_total().apply(self.track_total()),
+            genre: edit.genre().apply(self.genre()),
+            year: edit.year().apply(self.year()),
+            disc_number: edit.disc_number().apply(self.disc_number()),
+            disc_total: edit.disc_total().apply(self.disc_total()),
+            composer: edit.composer().apply(self.composer()),
+            comment: edit.comment().apply(self.comment()),
+            copyright: edit.copyright().apply(self.copyright()),
+            art: edit.art().apply(self.art()),
+            extra: ExtraField::ALL
+                .into_iter()
+                .filter_map(|field| {
+                    let current = self.extra.get(&field).cloned();
+                    let value = match edit.extra().get(&field) {
+                        Some(value) => value.apply(&current),
+                        None => current,
+                    };
+                    Some((field, value?))
+                })
+                .collect(),
+            custom: self
+                .custom
+                .keys()
+                .chain(edit.custom().keys())
+                .filter_map(|key| {
+                    let current = self.custom.get(key).cloned();
+                    let value = match edit.custom().get(key) {
+                        Some(value) => value.apply(&current),
+                        None => current,
+                    };
+                    Some((key.clone(), value?))
+                })
+                .collect(),
+            tag_type: self.tag_type,
+            tag_types: self.tag_types.clone(),
+            supported_tag_types: self.supported_tag_types.clone(),
+            write_to: self.write_to.clone(),
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Returns a copy as it stands once written to disk,
+    /// where any tags the save created now exist
+    pub fn after_write(&self) -> Self {
+        let mut written = self.clone();
+        for tag_type in &self.write_to {
+            if !written.tag_types.contains(tag_type) {
+                written.tag_types.push(*tag_type);
+            }
+        }
+        written
+    }
+}
+
+/// The state of a single field across one or more tracks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum FieldValue<T> {
+    /// Leave whatever value each track already has
+    #[default]
+    Unchanged,
+    /// Tracks hold differing values, each distinct value listed once.
+    /// `None` stands for tracks where the field is absent
+    Mixed(Vec<Option<T>>),
+    /// Every track holds, or will be given, this value
+    Set(T),
+    /// The field is absent from, or will be removed from, every track
+    Cleared,
+}
+
+impl<T: Clone + PartialEq> FieldValue<T> {
+    /// Consolidates the values a field holds across several tracks
+    pub fn from_values<'a, I>(values: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Option<T>>,
+        T: 'a,
+    {
+        let mut distinct: Vec<Option<T>> = Vec::new();
+        for value in values {
+            if !distinct.contains(value) {
+                distinct.push(value.clone());
+            }
+        }
+
+        match distinct.len() {
+            0 => FieldValue::Unchanged,
+            1 => match distinct.pop().unwrap() {
+                Some(value) => FieldValue::Set(value),
+                None => FieldValue::Cleared,
+            },
+            _ => FieldValue::Mixed(distinct),
+        }
+    }
+
+    /// Returns the value a track holding `current` should end up with
+    pub fn apply(&self, current: &Option<T>) -> Option<T> {
+        match self {
+            FieldValue::Unchanged | FieldValue::Mixed(_) => current.clone(),
+            FieldValue::Set(value) => Some(value.clone()),
+            FieldValue::Cleared => None,
+        }
+    }
+
+    /// Returns true if the tracks disagree on this field
+    pub fn is_mixed(&self) -> bool {
+        matches!(self, FieldValue::Mixed(_))
+    }
+
+    /// Returns true if applying this leaves each track's own value
+    pub fn keeps_current(&self) -> bool {
+        matches!(self, FieldValue::Unchanged | FieldValue::Mixed(_))
+    }
+
+    /// Works out a field's value from what's entered for it, where `None`
+    /// stands for an empty entry. `baseline` is the field as the tracks hold
+    /// it, so emptying an entry that showed differing values leaves each
+    /// track with its own again, rather than clearing them all
+    pub fn from_input(input: Option<T>, baseline: &Self) -> Self {
+        match input {
+            Some(value) => FieldValue::Set(value),
+            None if baseline.keeps_current() => baseline.clone(),
+            None => FieldValue::Cleared,
+        }
+    }
+}
+
+/// An edit to apply to one or more tracks' metadata.
+/// Built by `MetadataContainer::merge` and modified by the editor
+#[derive(Builder, Clone, Debug, Default, Getters, MutGetters, PartialEq, Setters)]
+#[get = "pub"]
+#[set = "pub"]
+#[allow(dead_code)]
+pub struct MetadataEdit {
+    title: FieldValue<String>,
+    artist: FieldValue<String>,
+    album_artist: FieldValue<String>,
+    album: FieldValue<String>,
+    track_number: FieldValue<i32>,
+    track_total: FieldValue<i32>,
+    genre: FieldValue<String>,
+    year: FieldValue<String>,
+    disc_number: FieldValue<i32>,
+    disc_total: FieldValue<i32>,
+    composer: FieldValue<String>,
+    comment: FieldValue<String>,
+    copyright: FieldValue<String>,
+    art: FieldValue<Vec<Art>>,
+    /// Extra fields missing from the map are left unchanged
+    #[builder(default)]
+    #[getset(get_mut = "pub")]
+    extra: BTreeMap<ExtraField, FieldValue<String>>,
+    /// Custom items missing from the map are left unchanged
+    #[builder(default)]
+    #[getset(get_mut = "pub")]
+    custom: BTreeMap<String, FieldValue<String>>,
+}
+
+#[derive(Builder, Clone, Debug, Getters, PartialEq, Setters)]
+#[get = "pub"]
+#[set = "pub"]
+#[allow(dead_code)]
+pub struct Art {
+    description: Option<String>,
+    /// What the image shows, such as the front cover or the artist
+    #[builder(default = "PictureType::CoverFront")]
+    pic_type: PictureType,
+    mime_type: String,
+    data: Vec<u8>,
+}
+
+impl Art {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path).map_err(|e| MetadataError::from_io(path, e))?;
+        Self::from_data(data).ok_or_else(|| MetadataError::NotAnImage(path.to_path_buf()))
+    }
+
+    /// Wraps image data, such as an image pasted from the clipboard, as
+    /// an undescribed front cover. The MIME type is read from the data
+    /// itself, and `None` is returned if it isn't a known image format
+    pub fn from_data(data: Vec<u8>) -> Option<Self> {
+        let mime_type = sniff_mime_type(&data)?;
+        Some(
+            ArtBuilder::default()
+                .description(None)
+                .pic_type(PictureType::CoverFront)
+                .mime_type(mime_type.into())
+                .data(data)
+                .build()
+                .expect("failed to build art"),
+        )
+    }
+
+    /// The file extension that suits the image's MIME type
+    pub fn extension(&self) -> &'static str {
+        match self.mime_type.as_str() {
+            "image/jpeg" | "image/jpg" => "jpg",
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/bmp" => "bmp",
+            "image/tiff" => "tiff",
+            "image/webp" => "webp",
+            mime_type => mime_guess::get_mime_extensions_str(mime_type)
+                .and_then(|extensions| extensions.first().copied())
+                .unwrap_or("bin"),
+        }
+    }
+
+    /// Writes the image to `path` exactly as the tag holds it
+    pub fn save_to_path(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, &self.data).map_err(|e| MetadataError::from_io(path, e))
+    }
+
+    /// Picks the image that stands for the album: the
+    /// front cover, or failing that the first image
+    pub fn cover(art: &[Art]) -> Option<&Art> {
+        art.iter()
+            .find(|art| art.pic_type == PictureType::CoverFront)
+            .or_else(|| art.first())
+    }
+}
+
+/// Plans a `cover.<ext>` file for each folder among the given tracks,
+/// taking the cover from the first track in the folder that has one
+pub fn cover_exports<'a>(
+    tracks: impl IntoIterator<Item = (&'a Path, &'a [Art])>,
+) -> Vec<(PathBuf, &'a Art)> {
+    let mut exports: Vec<(PathBuf, &Art)> = Vec::new();
+    for (path, art) in tracks {
+        let folder = match path.parent() {
+            Some(folder) => folder,
+            None => continue,
+        };
+        if exports
+            .iter()
+            .any(|(export, _)| export.parent() == Some(folder))
+        {
+            continue;
+        }
+        if let Some(cover) = Art::cover(art) {
+            exports.push((folder.join(format!("cover.{}", cover.extension())), cover));
+        }
+    }
+    exports
+}
+
+/// Every picture type an image can be given, in ID3v2 order
+pub const PICTURE_TYPES: [PictureType; 21] = [
+    PictureType::Other,
+    PictureType::Icon,
+    PictureType::OtherIcon,
+    PictureType::CoverFront,
+    PictureType::CoverBack,
+    PictureType::Leaflet,
+    PictureType::Media,
+    PictureType::LeadArtist,
+    PictureType::Artist,
+    PictureType::Conductor,
+    PictureType::Band,
+    PictureType::Composer,
+    PictureType::Lyricist,
+    PictureType::RecordingLocation,
+    PictureType::DuringRecording,
+    PictureType::DuringPerformance,
+    PictureType::ScreenCapture,
+    PictureType::BrightFish,
+    PictureType::Illustration,
+    PictureType::BandLogo,
+    PictureType::PublisherLogo,
+];
+
+/// The name a picture type is shown as
+pub fn picture_type_name(pic_type: PictureType) -> &'static str {
+    match pic_type {
+        PictureType::Other => "Other",
+        PictureType::Icon => "File Icon",
+        PictureType::OtherIcon => "Other File Icon",
+        PictureType::CoverFront => "Front Cover",
+        PictureType::CoverBack => "Back Cover",
+        PictureType::Leaflet => "Leaflet Page",
+        PictureType::Media => "Media",
+        PictureType::LeadArtist => "Lead Artist",
+        PictureType::Artist => "Artist",
+        PictureType::Conductor => "Conductor",
+        PictureType::Band => "Band",
+        PictureType::Composer => "Composer",
+        PictureType::Lyricist => "Lyricist",
+        PictureType::RecordingLocation => "Recording Location",
+        PictureType::DuringRecording => "During Recording",
+        PictureType::DuringPerformance => "During Performance",
+        PictureType::ScreenCapture => "Screen Capture",
+        PictureType::BrightFish => "Bright Coloured Fish",
+        PictureType::Illustration => "Illustration",
+        PictureType::BandLogo => "Band Logo",
+        PictureType::PublisherLogo => "Publisher Logo",
+        PictureType::Undefined(_) => "Undefined",
+        _ => "Unknown",
+    }
+}
+
+pub trait MetadataReadCapable {
+    /// Reads the file's primary tag
+    fn metadata(&self, path: &Path) -> Result<MetadataContainer>;
+    /// Reads one particular tag of the file, which is
+    /// treated as empty if the file doesn't have it
+    fn tag_metadata(&self, path: &Path, tag_type: TagType) -> Result<MetadataContainer>;
+}
+
+pub trait MetadataWriteCapable {
+    fn write_metadata(&self, path: &Path, metadata: &MetadataContainer) -> Result<()>;
+    /// Removes a whole tag from the file
+    fn remove_tag(&self, path: &Path, tag_type: TagType) -> Result<()>;
+}
+
+/// Every kind of tag lofty can read
+pub const TAG_TYPES: [TagType; 7] = [
+    TagType::Id3v2,
+    TagType::Id3v1,
+    TagType::Ape,
+    TagType::VorbisComments,
+    TagType::Mp4Ilst,
+    TagType::RiffInfo,
+    TagType::AiffText,
+];
+
+/// The name a kind of tag is known by
+pub fn tag_type_name(tag_type: TagType) -> &'static str {
+    match tag_type {
+        TagType::Id3v2 => "ID3v2",
+        TagType::Id3v1 => "ID3v1",
+        TagType::Ape => "APE",
+        TagType::VorbisComments => "Vorbis Comments",
+        TagType::Mp4Ilst => "MP4",
+        TagType::RiffInfo => "RIFF INFO",
+        TagType::AiffText => "AIFF Text",
+        _ => "Unknown",
+    }
+}
+
+#[derive(Builder, Debug, Default)]
+pub struct MetadataAgent {}
+
+impl MetadataAgent {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl MetadataReadCapable for MetadataAgent {
+    fn metadata(&self, path: &Path) -> Result<MetadataContainer> {
+        self.read_tag(path, None)
+    }
+
+    fn tag_metadata(&self, path: &Path, tag_type: TagType) -> Result<MetadataContainer> {
+        self.read_tag(path, Some(tag_type))
+    }
+}
+
+impl MetadataAgent {
+    // Reads the given tag, or the primary one if there's none given
+    fn read_tag(&self, path: &Path, tag_type: Option<TagType>) -> Result<MetadataContainer> {
+        let tagged_file = Probe::open(path)
+            .and_then(|probe| probe.read(true))
+            .map_err(|e| MetadataError::from_lofty(path, e))?;
+
+        let tag_type = tag_type.unwrap_or_else(|| tagged_file.primary_tag_type());
+        let tag = match tagged_file.tag(&tag_type) {
+            Some(tag) => tag.to_owned(),
+            None => Tag::new(tag_type),
+        };
+        let tag_types = tagged_file.tags().iter().map(|t| t.tag_type()).collect();
+        let supported_tag_types = TAG_TYPES
+            .into_iter()
+            .filter(|t| tagged_file.file_type().supports_tag_type(*t))
+            .collect();
+
+        // Handle art
+        let mut art = Vec::new();
+        for art_element in tag.pictures() {
+            // Tags can mislabel images, so the data is trusted over the label
+            let mime_type = match sniff_mime_type(art_element.data()) {
+                Some(mime_type) => mime_type.to_string(),
+                None => art_element.mime_type().to_string(),
+            };
+            let art_element = Art {
+                description: art_element.description().map(|d| d.to_string()),
+                pic_type: art_element.pic_type(),
+                mime_type,
+                data: art_element.data().to_vec(),
+            };
+
+            art.push(art_element);
+        }
+
+        let art = match !art.is_empty() {
+            true => Some(art),
+            false => None,
+        };
+
+        // Debug log metadata info
+        log::debug!("Found tagged item at {}", path.display());
+        for item in tag.items() {
+            log::debug!("{:?} - {:?}", item.key(), item.value());
+        }
+
+        // Numeric fields are read leniently, with anything
+        // unexpected reported as a warning rather than an error
+        let mut warnings = Vec::new();
+        let positions = self.read_positions(&tag, &mut warnings);
+
+        for warning in &warnings {
+            log::warn!("{}: {warning}", path.display());
+        }
+
+        let extra = ExtraField::ALL
+            .into_iter()
+            .filter_map(|field| {
+                let value = tag.get_string(&field.item_key())?;
+                Some((field, value.to_string()))
+            })
+            .collect();
+
+        // Only the first of any repeated custom items is shown.
+        // The writer leaves the rest alone
+        let mut custom = BTreeMap::new();
+        for item in tag.items() {
+            if let (Some(key), ItemValue::Text(value)) =
+                (custom_key(item.key(), tag.tag_type()), item.value())
+            {
+                custom.entry(key).or_insert_with(|| value.to_string());
+            }
+        }
+        if tag_type == TagType::Id3v2 {
+            for frame in user_text_frames(path, tagged_file.file_type())? {
+                if let FrameValue::UserText(text) = frame.content() {
+                    custom
+                        .entry(format!("{USER_TEXT}:{}", text.description))
+                        .or_insert_with(|| text.content.clone());
+                }
+            }
+        }
+
+        Ok(MetadataContainerBuilder::default()
+            .title(tag.title().map(|t| t.to_string()))
+            .artist(tag.artist().map(|a| a.to_string()))
+            .album(tag.album().map(|a| a.to_string()))
+            .album_artist(tag.get_string(&ItemKey::AlbumArtist).map(|a| a.to_string()))
+            .track_number(positions.track_number)
+            .track_total(positions.track_total)
+            .genre(tag.genre().map(|t| t.to_string()))
+            .year(tag.get_string(&year_key(tag_type)).map(|y| y.to_string()))
+            .disc_number(positions.disc_number)
+            .disc_total(positions.disc_total)
+            .composer(tag.get_string(&ItemKey::Composer).map(|c| c.to_string()))
+            .comment(tag.get_string(&ItemKey::Comment).map(|c| c.to_string()))
+            .copyright(
+                tag.get_string(&ItemKey::CopyrightMessage)
+                    .map(|c| c.to_string()),
+            )
+            .art(art)
+            .extra(extra)
+            .custom(custom)
+            .tag_type(Some(tag_type))
+            .tag_types(tag_types)
+            .supported_tag_types(supported_tag_types)
+            .write_to(vec![tag_type])
+            .warnings(warnings)
+            .build()
+            .expect("failed to build metadata container"))
+    }
+}
+
+impl MetadataWriteCapable for MetadataAgent {
+    fn write_metadata(&self, path: &Path, metadata: &MetadataContainer) -> Result<()> {
+        let permissions = std::fs::metadata(path)
+            .map_err(|e| MetadataError::from_io(path, e))?
+            .permissions();
+        if permissions.readonly() {
+            return Err(MetadataError::ReadOnly(path.to_path_buf()));
+        }
+
+        let mut tagged_file = Probe::open(path)
+            .and_then(|probe| probe.read(false))
+            .map_err(|e| MetadataError::from_lofty(path, e))?;
+
+        let write_to = match metadata.write_to().is_empty() {
+            true => vec![tagged_file.primary_tag_type()],
+            false => metadata.write_to().clone(),
+        };
+
+        // Saving a generic tag drops what tells user text frames apart,
+        // so they're read beforehand and written again on their own
+        let file_type = tagged_file.file_type();
+        let user_text = match write_to.contains(&TagType::Id3v2) {
+            true => user_text_frames(path, file_type)?,
+            false => Vec::new(),
+        };
+
+        // Nothing is written if a value would be lost on every tag
+        if let Some(field) = unheld_field(metadata, &write_to) {
+            let tag_types: Vec<&str> = write_to.iter().map(|t| tag_type_name(*t)).collect();
+            return Err(MetadataError::UnsupportedField {
+                path: path.to_path_buf(),
+                field,
+                tag_type: tag_types.join(" or "),
+            });
+        }
+
+        for tag_type in write_to {
+            // Files without the tag, such as fresh rips, are given one
+            if tagged_file.tag(&tag_type).is_none() {
+                if !tagged_file.file_type().supports_tag_type(tag_type) {
+                    return Err(MetadataError::UnsupportedTagType {
+                        path: path.to_path_buf(),
+                        tag_type: tag_type_name(tag_type).to_string(),
+                    });
+                }
+                tagged_file.insert_tag(Tag::new(tag_type));
+            }
+
+            let tag = tagged_file
+                .tag_mut(&tag_type)
+                .ok_or_else(|| MetadataError::NoTag(path.to_path_buf()))?;
+
+            self.write_tag(tag, metadata)?;
+            if tag_type == TagType::Id3v2 {
+                tag.remove_key(&ItemKey::Unknown(USER_TEXT.to_string()));
+            }
+            tag.save_to_path(path)
+                .map_err(|e| MetadataError::from_lofty(path, e))?;
+
+            if tag_type == TagType::Id3v2 {
+                let frames = match *metadata.tag_type() == Some(tag_type) {
+                    true => custom_user_text(path, metadata.custom(), &user_text)?,
+                    false => user_text.clone(),
+                };
+                write_user_text(path, file_type, frames)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove_tag(&self, path: &Path, tag_type: TagType) -> Result<()> {
+        tag_type
+            .remove_from_path(path)
+            .map_err(|e| MetadataError::from_lofty(path, e))
+    }
+}
+
+impl MetadataAgent {
+    fn write_tag(&self, tag: &mut Tag, metadata: &MetadataContainer) -> Result<()> {
+        let tag_items = [
+            (ItemKey::TrackTitle, metadata.title()),
+            (ItemKey::TrackArtist, metadata.artist()),
+            (ItemKey::AlbumTitle, metadata.album()),
+            (ItemKey::AlbumArtist, metadata.album_artist()),
+            (ItemKey::Genre, metadata.genre()),
+            (year_key(tag.tag_type()), metadata.year()),
+            (ItemKey::Composer, metadata.composer()),
+            (ItemKey::CopyrightMessage, metadata.copyright()),
+            (ItemKey::Comment, metadata.comment()),
+        ];
+
+        for tag_item in tag_items {
+            self.write_text_value(tag, tag_item);
+        }
+
+        // Positions are only rewritten when they've been changed, so values
+        // that couldn't be read in full, such as "B2", are kept as they are
+        let current = self.read_positions(tag, &mut Vec::new());
+        let numbers = [
+            (
+                (ItemKey::TrackNumber, metadata.track_number()),
+                (ItemKey::TrackTotal, metadata.track_total()),
+                (current.track_number, current.track_total),
+            ),
+            (
+                (ItemKey::DiscNumber, metadata.disc_number()),
+                (ItemKey::DiscTotal, metadata.disc_total()),
+                (current.disc_number, current.disc_total),
+            ),
+        ];
+        for ((number_key, number), (total_key, total), current) in numbers {
+            if (*number, *total) != current {
+                self.write_text_value(tag, (number_key, &number.map(|n| n.to_string())));
+                self.write_text_value(tag, (total_key, &total.map(|t| t.to_string())));
+            }
+        }
+
+        for field in ExtraField::ALL {
+            let value = metadata.extra().get(&field).cloned();
+            self.write_text_value(tag, (field.item_key(), &value));
+        }
+
+        // Custom items are keyed for the tag they were read from,
+        // so other kinds of tag are left with their own
+        if *metadata.tag_type() == Some(tag.tag_type()) {
+            self.write_custom_items(tag, metadata.custom())?;
+        }
+
+        self.write_art(tag, metadata.art())
+    }
+
+    // Reads the track and disc positions, where a total can
+    // come from its own item or share one with the number
+    fn read_positions(&self, tag: &Tag, warnings: &mut Vec<MetadataWarning>) -> Positions {
+        let track = self.read_position(tag, ItemKey::TrackNumber, "track number", warnings);
+        let track_total = self.read_position(tag, ItemKey::TrackTotal, "track total", warnings);
+        let disc = self.read_position(tag, ItemKey::DiscNumber, "disc number", warnings);
+        let disc_total = self.read_position(tag, ItemKey::DiscTotal, "disc total", warnings);
+
+        Positions {
+            track_number: track.number,
+            track_total: track_total.number.or(track.total),
+            disc_number: disc.number,
+            disc_total: disc_total.number.or(disc.total),
+        }
+    }
+
+    fn read_position(
+        &self,
+        tag: &Tag,
+        key: ItemKey,
+        field: &str,
+        warnings: &mut Vec<MetadataWarning>,
+    ) -> Position {
+        let value = match tag.get_string(&key) {
+            Some(value) => value,
+            None => return Position::default(),
+        };
+
+        match Position::parse(value) {
+            Some(position) => {
+                if let Some(side) = position.side {
+                    warnings.push(MetadataWarning::VinylSide {
+                        field: field.to_string(),
+                        value: value.to_string(),
+                        side,
+                    });
+                }
+                position
+            }
+            None => {
+                warnings.push(MetadataWarning::Unparseable {
+                    field: field.to_string(),
+                    value: value.to_string(),
+                });
+                Position::default()
+            }
+        }
+    }
+
+    // Keys the tag can't hold are skipped, as lofty would otherwise
+    // store them under another key's name, such as ID3v2's TEXT for
+    // both writer and lyricist
+    fn write_text_value(&self, tag: &mut Tag, tag_item: (ItemKey, &Option<String>)) {
+        if !tag_holds(tag.tag_type(), &tag_item.0) {
+            return;
+        }
+
+        match tag_item.1 {
+            Some(t) => {
+                tag.insert_item(TagItem::new(tag_item.0, ItemValue::Text(t.to_string())));
+            }
+            None => tag.remove_key(&tag_item.0),
+        }
+    }
+
+    fn write_custom_items(&self, tag: &mut Tag, custom: &BTreeMap<String, String>) -> Result<()> {
+        let tag_type = tag.tag_type();
+
+        // Removes custom items that are no longer wanted
+        let removed: Vec<ItemKey> = tag
+            .items()
+            .iter()
+            .filter(|item| matches!(item.value(), ItemValue::Text(_)))
+            .filter(|item| {
+                custom_key(item.key(), tag_type).is_some_and(|key| !custom.contains_key(&key))
+            })
+            .map(|item| item.key().clone())
+            .collect();
+        for key in removed {
+            tag.remove_key(&key);
+        }
+
+        for (key, value) in custom {
+            // User text frames are written on their own
+            if tag_type == TagType::Id3v2 && user_text_description(key).is_some() {
+                continue;
+            }
+
+            let item_key = ItemKey::from_key(tag_type, key);
+            if is_field_key(&item_key) {
+                return Err(MetadataError::InvalidFieldValue {
+                    field: key.clone(),
+                    reason: "it has a field of its own".to_string(),
+                });
+            }
+
+            // Untouched items are skipped, so repeated values survive
+            if tag.get_string(&item_key) == Some(value.as_str()) {
+                continue;
+            }
+
+            // lofty only inserts keys it has a name for, so other keys
+            // are inserted as they are where the tag's format allows them
+            let item = TagItem::new(item_key.clone(), ItemValue::Text(value.clone()));
+            if !tag.insert_item(item.clone()) {
+                if !matches!(item_key, ItemKey::Unknown(_)) || !accepts_unknown_key(tag_type, key) {
+                    return Err(MetadataError::InvalidFieldValue {
+                        field: key.clone(),
+                        reason: format!("{} tags can't hold this key", tag_type_name(tag_type)),
+                    });
+                }
+                tag.insert_item_unchecked(item);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_art(&self, tag: &mut Tag, art_items: &Option<Vec<Art>>) -> Result<()> {
+        let mut pic_types = Vec::new();
+        for existing_picture in tag.pictures() {
+            pic_types.push(existing_picture.pic_type());
+        }
+        pic_types.dedup();
+
+        // Remove existing art
+        for pic_type in pic_types {
+            tag.remove_picture_type(pic_type);
+        }
+
+        if let Some(art) = art_items {
+            for art_item in art {
+                if art_item.data().is_empty() {
+                    return Err(MetadataError::InvalidFieldValue {
+                        field: "art".to_string(),
+                        reason: "the image has no data".to_string(),
+                    });
+                }
+
+                tag.push_picture(lofty::Picture::new_unchecked(
+                    art_item.pic_type,
+                    lofty::MimeType::from_str(art_item.mime_type()),
+                    art_item.description().to_owned(),
+                    art_item.data().to_vec(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Track and disc positions as MetadataContainer holds them
+struct Positions {
+    track_number: Option<i32>,
+    track_total: Option<i32>,
+    disc_number: Option<i32>,
+    disc_total: Option<i32>,
+}
+
+// The ID3v2 frame holding user text, told apart by its description
+const USER_TEXT: &str = "TXXX";
+
+// The description a custom key names for a user text frame, if it names one
+fn user_text_description(key: &str) -> Option<&str> {
+    match key.strip_prefix(USER_TEXT) {
+        Some("") => Some(""),
+        Some(rest) => rest.strip_prefix(':'),
+        None => None,
+    }
+}
+
+// Reads the ID3v2 tag of the file frame by frame, as lofty parses it
+fn read_id3v2(path: &Path, file_type: FileType) -> Result<Option<Id3v2Tag>> {
+    let mut file = File::open(path).map_err(|e| MetadataError::from_io(path, e))?;
+    let tag = match file_type {
+        FileType::MP3 => Mp3File::read_from(&mut file, false).map(|f| f.id3v2_tag().cloned()),
+        FileType::WAV => WavFile::read_from(&mut file, false).map(|f| f.id3v2_tag().cloned()),
+        FileType::AIFF => AiffFile::read_from(&mut file, false).map(|f| f.id3v2_tag().cloned()),
+        _ => Ok(None),
+    };
+    tag.map_err(|e| MetadataError::from_lofty(path, e))
+}
+
+fn user_text_frames(path: &Path, file_type: FileType) -> Result<Vec<Frame>> {
+    let frames = read_id3v2(path, file_type)?
+        .map(|tag| {
+            tag.iter()
+                .filter(|frame| frame.id_str() == USER_TEXT)
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(frames)
+}
+
+// The user text frames the custom items ask for, keeping
+// the ones that are already there as they are
+fn custom_user_text(
+    path: &Path,
+    custom: &BTreeMap<String, String>,
+    existing: &[Frame],
+) -> Result<Vec<Frame>> {
+    let mut frames = Vec::new();
+    for (key, content) in custom {
+        let description = match user_text_description(key) {
+            Some(description) => description,
+            None => continue,
+        };
+
+        let unchanged = existing.iter().find(|frame| {
+            matches!(frame.content(), FrameValue::UserText(text)
+                if text.description == description && text.content == *content)
+        });
+        let frame = match unchanged {
+            Some(frame) => frame.clone(),
+            None => {
+                let text = EncodedTextFrame {
+                    encoding: TextEncoding::UTF8,
+                    description: description.to_string(),
+                    content: content.clone(),
+                };
+                Frame::new(USER_TEXT, FrameValue::UserText(text), FrameFlags::default())
+                    .map_err(|e| MetadataError::from_lofty(path, e))?
+            }
+        };
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+// Replaces the user text frames in the file's ID3v2 tag
+fn write_user_text(path: &Path, file_type: FileType, frames: Vec<Frame>) -> Result<()> {
+    let mut tag = match read_id3v2(path, file_type)? {
+        Some(tag) => tag,
+        None if frames.is_empty() => return Ok(()),
+        None => Id3v2Tag::default(),
+    };
+
+    tag.remove(USER_TEXT);
+    for frame in frames {
+        tag.insert(frame);
+    }
+    tag.save_to_path(path)
+        .map_err(|e| MetadataError::from_lofty(path, e))
+}
+
+// The key the year is kept under, which is the recording date
+// wherever the tag has one
+fn year_key(tag_type: TagType) -> ItemKey {
+    match ItemKey::RecordingDate.map_key(tag_type, false) {
+        Some(_) => ItemKey::RecordingDate,
+        None => ItemKey::Year,
+    }
+}
+
+/// Whether tags of the given type can hold the key as an item of its own
+pub fn tag_holds(tag_type: TagType, key: &ItemKey) -> bool {
+    let item = TagItem::new(key.clone(), ItemValue::Text(String::new()));
+    if !Tag::new(tag_type).insert_item(item) {
+        return false;
+    }
+
+    // Numbers and totals share an item on purpose in some tags
+    if matches!(
+        key,
+        ItemKey::TrackNumber | ItemKey::TrackTotal | ItemKey::DiscNumber | ItemKey::DiscTotal
+    ) {
+        return true;
+    }
+    match key.map_key(tag_type, false) {
+        Some(name) => ItemKey::from_key(tag_type, name) == *key,
+        None => true,
+    }
+}
+
+// The first field with a value that none of the tags can hold
+fn unheld_field(metadata: &MetadataContainer, tag_types: &[TagType]) -> Option<String> {
+    let texts = [
+        ("title", ItemKey::TrackTitle, metadata.title()),
+        ("artist", ItemKey::TrackArtist, metadata.artist()),
+        ("album", ItemKey::AlbumTitle, metadata.album()),
+        (
+            "album artist",
+            ItemKey::AlbumArtist,
+            metadata.album_artist(),
+        ),
+        ("genre", ItemKey::Genre, metadata.genre()),
+        ("year", ItemKey::Year, metadata.year()),
+        ("composer", ItemKey::Composer, metadata.composer()),
+        ("comment", ItemKey::Comment, metadata.comment()),
+        ("copyright", ItemKey::CopyrightMessage, metadata.copyright()),
+    ];
+    let numbers = [
+        (
+            "track number",
+            ItemKey::TrackNumber,
+            metadata.track_number(),
+        ),
+        ("track total", ItemKey::TrackTotal, metadata.track_total()),
+        ("disc number", ItemKey::DiscNumber, metadata.disc_number()),
+        ("disc total", ItemKey::DiscTotal, metadata.disc_total()),
+    ];
+    let texts = texts.into_iter().filter(|(_, _, value)| value.is_some());
+    let numbers = numbers.into_iter().filter(|(_, _, value)| value.is_some());
+    let extras = metadata
+        .extra()
+        .keys()
+        .map(|field| (field.label(), field.item_key()));
+
+    texts
+        .map(|(name, key, _)| (name, key))
+        .chain(numbers.map(|(name, key, _)| (name, key)))
+        .chain(extras)
+        .find(|(_, key)| {
+            !tag_types.iter().any(|tag_type| match key {
+                ItemKey::Year => tag_holds(*tag_type, &year_key(*tag_type)),
+                key => tag_holds(*tag_type, key),
+            })
+        })
+        .map(|(name, _)| name.to_lowercase())
+}
+
+// Keys edited through MetadataContainer's own fields
+const FIELD_KEYS: [ItemKey; 14] = [
+    ItemKey::TrackTitle,
+    ItemKey::TrackArtist,
+    ItemKey::AlbumTitle,
+    ItemKey::AlbumArtist,
+    ItemKey::TrackNumber,
+    ItemKey::TrackTotal,
+    ItemKey::Genre,
+    ItemKey::Year,
+    ItemKey::RecordingDate,
+    ItemKey::DiscNumber,
+    ItemKey::DiscTotal,
+    ItemKey::Composer,
+    ItemKey::Comment,
+    ItemKey::CopyrightMessage,
+];
+
+fn is_field_key(key: &ItemKey) -> bool {
+    FIELD_KEYS.contains(key) || ExtraField::ALL.iter().any(|f| &f.item_key() == key)
+}
+
+// Whether a key lofty has no name for can be stored in the tag as it is
+fn accepts_unknown_key(tag_type: TagType, key: &str) -> bool {
+    match tag_type {
+        TagType::VorbisComments | TagType::Ape => {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_graphic() && c != '=')
+        }
+        TagType::Id3v2 | TagType::RiffInfo | TagType::AiffText => {
+            key.len() == 4
+                && key
+                    .chars()
+                    .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        }
+        _ => false,
+    }
+}
+
+// The name a custom item goes by, or `None` if a field covers it
+// or it's a user text frame, which is read on its own
+fn custom_key(key: &ItemKey, tag_type: TagType) -> Option<String> {
+    if is_field_key(key) || *key == ItemKey::Unknown(USER_TEXT.to_string()) {
+        return None;
+    }
+    key.map_key(tag_type, true).map(|k| k.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tempfile::TempDir;
+
+    // Copies a short, untagged file of the given format into the folder
+    fn fixture(dir: &TempDir, extension: &str) -> PathBuf {
+        let name = format!("empty.{extension}");
+        let path = dir.path().join(format!("track.{extension}"));
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        std::fs::copy(fixture, &path).unwrap();
+        path
+    }
+
+    #[test]
+    fn merge_marks_differing_fields_as_mixed() {
+        let first = MetadataContainer {
+            title: Some("<Keep>".to_string()),
+            track_number: Some(1),
+            ..Default::default()
+        };
+        let second = MetadataContainer {
+            title: Some("<Keep>".to_string()),
+            track_number: Some(-1),
+            ..Default::default()
+        };
+
+        let merged = MetadataContainer::merge(&[first, second]);
+        assert_eq!(merged.title(), &FieldValue::Set("<Keep>".to_string()));
+        assert_eq!(
+            merged.track_number(),
+            &FieldValue::Mixed(vec![Some(1), Some(-1)])
+        );
+        assert_eq!(merged.genre(), &FieldValue::Cleared);
+    }
+
+    #[test]
+    fn apply_keeps_mixed_fields() {
+        let current = MetadataContainer {
+            title: Some("Title".to_string()),
+            artist: Some("Artist".to_string()),
+            ..Default::default()
+        };
+
+        let mut edit = MetadataEdit::default();
+        edit.set_title(FieldValue::Mixed(vec![None, Some("Other".to_string())]));
+        edit.set_artist(FieldValue::Cleared);
+
+        let applied = current.apply(&edit);
+        assert_eq!(applied.title(), &Some("Title".to_string()));
+        assert_eq!(applied.artist(), &None);
+    }
+
+    #[test]
+    fn emptied_entries_leave_mixed_fields_alone() {
+        let tracks = [
+            MetadataContainer {
+                title: Some("One".to_string()),
+                album: Some("Album".to_string()),
+                ..Default::default()
+            },
+            MetadataContainer {
+                title: Some("Two".to_string()),
+                album: Some("Album".to_string()),
+                ..Default::default()
+            },
+        ];
+        let baseline = MetadataContainer::merge(&tracks);
+
+        // Typing into the field sets it for every track...
+        let mut edit = baseline.clone();
+        edit.set_title(FieldValue::from_input(
+            Some("Typo".to_string()),
+            baseline.title(),
+        ));
+        assert_eq!(tracks[0].apply(&edit).title(), &Some("Typo".to_string()));
+
+        // ...until the entry is emptied again, while emptying
+        // a field the tracks share still clears it
+        edit.set_title(FieldValue::from_input(None, baseline.title()));
+        edit.set_album(FieldValue::from_input(None, baseline.album()));
+        let saved: Vec<MetadataContainer> = tracks.iter().map(|t| t.apply(&edit)).collect();
+        assert_eq!(saved[0].title(), &Some("One".to_string()));
+        assert_eq!(saved[1].title(), &Some("Two".to_string()));
+        assert_eq!(saved[0].album(), &None);
+    }
+
+    #[test]
+    fn extra_fields_merge_and_apply() {
+        let mut first = MetadataContainer::default();
+        first.extra.insert(ExtraField::Lyricist, "A".to_string());
+        first.extra.insert(ExtraField::Mood, "Calm".to_string());
+        let mut second = first.clone();
+        second.extra.insert(ExtraField::Lyricist, "B".to_string());
+
+        let mut merged = MetadataContainer::merge(&[first.clone(), second]);
+        assert!(merged.extra()[&ExtraField::Lyricist].is_mixed());
+        assert_eq!(
+            merged.extra()[&ExtraField::Mood],
+            FieldValue::Set("Calm".to_string())
+        );
+
+        merged.extra.insert(ExtraField::Mood, FieldValue::Cleared);
+        let applied = first.apply(&merged);
+        assert_eq!(
+            applied.extra().get(&ExtraField::Lyricist),
+            Some(&"A".to_string())
+        );
+        assert_eq!(applied.extra().get(&ExtraField::Mood), None);
+    }
+
+    #[test]
+    fn custom_items_survive_a_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = fixture(&dir, "flac");
+        let agent = MetadataAgent::new();
+
+        // Gives the file a Vorbis comment block to write to
+        let mut tagged_file = Probe::open(&path).unwrap().read(false).unwrap();
+        let mut tag = Tag::new(TagType::VorbisComments);
+        tag.insert_item(TagItem::new(
+            ItemKey::TrackArtist,
+            ItemValue::Text("Band".to_string()),
+        ));
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&path).unwrap();
+
+        let mut metadata = agent.metadata(&path).unwrap();
+        metadata.set_title(Some("Song".to_string()));
+        metadata
+            .custom
+            .insert("SOURCE".to_string(), "Vinyl".to_string());
+        agent.write_metadata(&path, &metadata).unwrap();
+
+        let mut metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.title(), &Some("Song".to_string()));
+        assert_eq!(metadata.custom().get("SOURCE"), Some(&"Vinyl".to_string()));
+
+        metadata.custom.clear();
+        agent.write_metadata(&path, &metadata).unwrap();
+        assert!(agent.metadata(&path).unwrap().custom().is_empty());
+    }
+
+    #[test]
+    fn unreadable_positions_survive_other_edits() {
+        let dir = TempDir::new().unwrap();
+        let path = fixture(&dir, "flac");
+
+        let mut tagged_file = Probe::open(&path).unwrap().read(false).unwrap();
+        let mut tag = Tag::new(TagType::VorbisComments);
+        tag.insert_item(TagItem::new(
+            ItemKey::TrackNumber,
+            ItemValue::Text("B2".to_string()),
+        ));
+        tag.insert_item(TagItem::new(
+            ItemKey::DiscNumber,
+            ItemValue::Text("Side one".to_string()),
+        ));
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&path).unwrap();
+
+        let agent = MetadataAgent::new();
+        let mut metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.track_number(), &Some(2));
+        assert_eq!(metadata.disc_number(), &None);
+        assert_eq!(metadata.warnings().len(), 2);
+
+        metadata.set_title(Some("Song".to_string()));
+        agent.write_metadata(&path, &metadata).unwrap();
+
+        let read_item = |key: ItemKey| {
+            let tagged_file = Probe::open(&path).unwrap().read(false).unwrap();
+            let tag = tagged_file.tag(&TagType::VorbisComments).unwrap();
+            tag.get_string(&key).map(|value| value.to_string())
+        };
+        assert_eq!(read_item(ItemKey::TrackNumber), Some("B2".to_string()));
+        assert_eq!(read_item(ItemKey::DiscNumber), Some("Side one".to_string()));
+        assert_eq!(read_item(ItemKey::TrackTitle), Some("Song".to_string()));
+
+        metadata.set_track_number(Some(3));
+        agent.write_metadata(&path, &metadata).unwrap();
+        assert_eq!(read_item(ItemKey::TrackNumber), Some("3".to_string()));
+        assert_eq!(read_item(ItemKey::DiscNumber), Some("Side one".to_string()));
+    }
+
+    #[test]
+    fn mp3_fields_survive_a_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = fixture(&dir, "mp3");
+        let agent = MetadataAgent::new();
+
+        // Gives the file an ID3v2 tag to write to
+        let mut tagged_file = Probe::open(&path).unwrap().read(false).unwrap();
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.insert_item(TagItem::new(
+            ItemKey::TrackTitle,
+            ItemValue::Text("Song".to_string()),
+        ));
+        tagged_file.insert_tag(tag);
+        tagged_file.save_to_path(&path).unwrap();
+
+        let mut metadata = agent.metadata(&path).unwrap();
+        metadata.set_year(Some("1999".to_string()));
+        metadata
+            .extra
+            .insert(ExtraField::Lyricist, "Lyricist".to_string());
+        metadata
+            .extra
+            .insert(ExtraField::Conductor, "Conductor".to_string());
+        agent.write_metadata(&path, &metadata).unwrap();
+
+        let mut metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.year(), &Some("1999".to_string()));
+        assert_eq!(
+            metadata.extra().get(&ExtraField::Lyricist),
+            Some(&"Lyricist".to_string())
+        );
+        assert_eq!(
+            metadata.extra().get(&ExtraField::Conductor),
+            Some(&"Conductor".to_string())
+        );
+
+        assert!(ExtraField::Lyricist.held_by(TagType::Id3v2));
+        assert!(!ExtraField::Writer.held_by(TagType::Id3v2));
+        assert!(!ExtraField::Arranger.held_by(TagType::Id3v2));
+        assert!(ExtraField::Arranger.held_by(TagType::VorbisComments));
+
+        metadata
+            .extra
+            .insert(ExtraField::Arranger, "Arranger".to_string());
+        assert!(matches!(
+            agent.write_metadata(&path, &metadata),
+            Err(MetadataError::UnsupportedField { .. })
+        ));
+        assert_eq!(agent.metadata(&path).unwrap().extra().len(), 2);
+    }
+
+    #[test]
+    fn user_text_frames_keep_their_descriptions() {
+        let dir = TempDir::new().unwrap();
+        let path = fixture(&dir, "mp3");
+
+        let mut tag = Id3v2Tag::default();
+        for (description, content) in [("SOURCE", "Vinyl"), ("MOOD", "Calm")] {
+            let text = EncodedTextFrame {
+                encoding: TextEncoding::UTF8,
+                description: description.to_string(),
+                content: content.to_string(),
+            };
+            let frame = Frame::new(USER_TEXT, FrameValue::UserText(text), FrameFlags::default());
+            tag.insert(frame.unwrap());
+        }
+        tag.save_to_path(&path).unwrap();
+
+        let agent = MetadataAgent::new();
+        let mut metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.custom().len(), 2);
+        assert_eq!(metadata.custom()["TXXX:SOURCE"], "Vinyl");
+        assert_eq!(metadata.custom()["TXXX:MOOD"], "Calm");
+
+        metadata.set_title(Some("Song".to_string()));
+        metadata
+            .custom
+            .insert("TXXX:SOURCE".to_string(), "CD".to_string());
+        agent.write_metadata(&path, &metadata).unwrap();
+
+        let mut metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.title(), &Some("Song".to_string()));
+        assert_eq!(metadata.custom().len(), 2);
+        assert_eq!(metadata.custom()["TXXX:SOURCE"], "CD");
+        assert_eq!(metadata.custom()["TXXX:MOOD"], "Calm");
+
+        metadata.custom.remove("TXXX:SOURCE");
+        agent.write_metadata(&path, &metadata).unwrap();
+        let metadata = agent.metadata(&path).unwrap();
+        assert_eq!(metadata.custom().len(), 1);
+        assert_eq!(metadata.custom()["TXXX:MOOD"], "Calm");
+    }
+
+    #[test]
+    fn custom_items_can_be_added_and_removed() {
+        let mut current = MetadataContainer::default();
+        current
+            .custom
+            .insert("REPLAYGAIN_TRACK_GAIN".to_string(), "-6.2 dB".to_string());
+
+        let mut edit = MetadataEdit::default();
+        edit.custom_mut()
+            .insert("REPLAYGAIN_TRACK_GAIN".to_string(), FieldValue::Cleared);
+        edit.custom_mut()
+            .insert("SOURCE".to_string(), FieldValue::Set("CD".to_string()));
+
+        let applied = current.apply(&edit);
+        assert_eq!(applied.custom().len(), 1);
+        assert_eq!(applied.custom()["SOURCE"], "CD");
+    }
+
+    #[test]
+    fn after_write_lists_created_tags() {
+        let metadata = MetadataContainer {
+            tag_types: vec![TagType::Id3v1],
+            write_to: vec![TagType::Id3v2, TagType::Id3v1],
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.after_write().tag_types(),
+            &vec![TagType::Id3v1, TagType::Id3v2]
+        );
+    }
+
+    #[test]
+    fn cover_exports_one_cover_per_folder() {
+        let image = |pic_type, mime_type: &str| {
+            ArtBuilder::default()
+                .description(None)
+                .pic_type(pic_type)
+                .mime_type(mime_type.to_string())
+                .data(vec![])
+                .build()
+                .unwrap()
+        };
+        let first = [
+            image(PictureType::CoverBack, "image/png"),
+            image(PictureType::CoverFront, "image/jpeg"),
+        ];
+        let second = [image(PictureType::CoverFront, "image/png")];
+        let other = [image(PictureType::Artist, "image/png")];
+
+        let exports = cover_exports([
+            (Path::new("/music/a/01.mp3"), &[][..]),
+            (Path::new("/music/a/02.mp3"), &first[..]),
+            (Path::new("/music/a/03.mp3"), &second[..]),
+            (Path::new("/music/b/01.mp3"), &other[..]),
+        ]);
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports[0].0, Path::new("/music/a/cover.jpg"));
+        assert_eq!(exports[0].1, &first[1]);
+        assert_eq!(exports[1].0, Path::new("/music/b/cover.png"));
+    }
+}
@@ -0,0 +1,219 @@
+// numbering.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use derive_builder::Builder;
+use getset::CopyGetters;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The order tracks are numbered in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NumberingOrder {
+    /// The order the tracks are given in
+    Given,
+    /// Folder by folder, in the order of the tracks' file names,
+    /// with numbers in them compared by value
+    FileName,
+}
+
+/// Where numbering starts over
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NumberingRestart {
+    Never,
+    EachDisc,
+    EachFolder,
+}
+
+/// How tracks are numbered
+#[derive(Builder, Clone, Copy, Debug, CopyGetters, PartialEq)]
+#[get_copy = "pub"]
+pub struct NumberingOptions {
+    #[builder(default = "1")]
+    start: i32,
+    #[builder(default = "1")]
+    step: i32,
+    #[builder(default = "NumberingOrder::Given")]
+    order: NumberingOrder,
+    #[builder(default = "NumberingRestart::Never")]
+    restart: NumberingRestart,
+    /// Whether each track's total is set to the
+    /// number of tracks numbered alongside it
+    #[builder(default)]
+    fill_total: bool,
+}
+
+/// A track to be numbered: its path and disc number
+pub type NumberingTrack = (PathBuf, Option<i32>);
+
+/// Works out each track's number, and its total if asked for,
+/// returned in the order the tracks are given in
+pub fn auto_number(
+    tracks: &[NumberingTrack],
+    options: &NumberingOptions,
+) -> Vec<(i32, Option<i32>)> {
+    let mut order: Vec<usize> = (0..tracks.len()).collect();
+    if options.order() == NumberingOrder::FileName {
+        order.sort_by(|a, b| {
+            let (a, b) = (&tracks[*a].0, &tracks[*b].0);
+            a.parent()
+                .cmp(&b.parent())
+                .then_with(|| {
+                    let name = |path: &Path| {
+                        path.file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default()
+                    };
+                    natural_cmp(&name(a), &name(b))
+                })
+                .then_with(|| a.cmp(b))
+        });
+    }
+
+    let group = |(path, disc): &NumberingTrack| match options.restart() {
+        NumberingRestart::Never => String::new(),
+        NumberingRestart::EachDisc => format!("{disc:?}"),
+        NumberingRestart::EachFolder => path
+            .parent()
+            .map(Path::to_string_lossy)
+            .unwrap_or_default()
+            .to_string(),
+    };
+
+    let mut totals: HashMap<String, i32> = HashMap::new();
+    for track in tracks {
+        *totals.entry(group(track)).or_default() += 1;
+    }
+
+    let mut counts: HashMap<String, i32> = HashMap::new();
+    let mut numbers = vec![(0, None); tracks.len()];
+    for i in order {
+        let key = group(&tracks[i]);
+        let count = counts.entry(key.clone()).or_default();
+        let number = options.start() + *count * options.step();
+        *count += 1;
+
+        let total = options.fill_total().then(|| totals[&key]);
+        numbers[i] = (number, total);
+    }
+    numbers
+}
+
+// Compares text the way people read it, so "2 x" comes before "10 x"
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let digits = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut run = String::new();
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        run.push(c);
+                    }
+                    run.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (digits(&mut a), digits(&mut b));
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase()).then(x.cmp(&y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn tracks() -> Vec<NumberingTrack> {
+        vec![
+            (PathBuf::from("/a/2.mp3"), Some(1)),
+            (PathBuf::from("/a/1.mp3"), Some(1)),
+            (PathBuf::from("/b/1.mp3"), Some(2)),
+        ]
+    }
+
+    #[test]
+    fn numbers_follow_start_step_and_order() {
+        let options = NumberingOptionsBuilder::default()
+            .start(10)
+            .step(5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            auto_number(&tracks(), &options),
+            vec![(10, None), (15, None), (20, None)]
+        );
+
+        let options = NumberingOptionsBuilder::default()
+            .order(NumberingOrder::FileName)
+            .fill_total(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            auto_number(&tracks(), &options),
+            vec![(2, Some(3)), (1, Some(3)), (3, Some(3))]
+        );
+    }
+
+    #[test]
+    fn file_names_are_ordered_naturally() {
+        let tracks = vec![
+            (PathBuf::from("/b/1 x.mp3"), None),
+            (PathBuf::from("/a/10 x.mp3"), None),
+            (PathBuf::from("/a/2 x.mp3"), None),
+            (PathBuf::from("/a/B.mp3"), None),
+            (PathBuf::from("/a/a.mp3"), None),
+        ];
+        let options = NumberingOptionsBuilder::default()
+            .order(NumberingOrder::FileName)
+            .build()
+            .unwrap();
+        assert_eq!(
+            auto_number(&tracks, &options),
+            vec![(5, None), (2, None), (1, None), (4, None), (3, None)]
+        );
+    }
+
+    #[test]
+    fn numbering_restarts_per_group() {
+        let options = NumberingOptionsBuilder::default()
+            .restart(NumberingRestart::EachFolder)
+            .fill_total(true)
+            .build()
+            .unwrap();
+        assert_eq!(
+            auto_number(&tracks(), &options),
+            vec![(1, Some(2)), (2, Some(2)), (1, Some(1))]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+// position.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fmt;
+
+/// A track or disc position as found in a tag, such as
+/// "3", " 03 ", "3/12" or the vinyl-style "B2"
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    pub number: Option<i32>,
+    pub total: Option<i32>,
+    pub side: Option<char>,
+}
+
+impl Position {
+    /// Parses a position, returning `None` if the value isn't one.
+    /// An empty value parses to an empty position
+    pub fn parse(value: &str) -> Option<Self> {
+        let (number, total) = match value.split_once('/') {
+            Some((number, total)) => (number, Some(total)),
+            None => (value, None),
+        };
+
+        let total = match total.map(str::trim) {
+            Some("") | None => None,
+            Some(total) => Some(parse_number(total)?),
+        };
+
+        let number = number.trim();
+        if number.is_empty() {
+            return Some(Self {
+                total,
+                ..Default::default()
+            });
+        }
+
+        // Vinyl sides are written as a letter followed by the
+        // position on that side, like "A1" or "b12"
+        let mut chars = number.chars();
+        let first = chars.next()?;
+        let (side, number) = if first.is_ascii_alphabetic() {
+            (Some(first.to_ascii_uppercase()), chars.as_str().trim())
+        } else {
+            (None, number)
+        };
+
+        Some(Self {
+            number: Some(parse_number(number)?),
+            total,
+            side,
+        })
+    }
+}
+
+fn parse_number(value: &str) -> Option<i32> {
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    value.parse::<i32>().ok()
+}
+
+/// A problem found while reading a file's tags
+/// that didn't stop the rest of it being read
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetadataWarning {
+    /// A numeric field held a value that couldn't be understood,
+    /// so the field was left empty
+    Unparseable { field: String, value: String },
+    /// A position used a vinyl side such as "B2".
+    /// Only the number within the side was kept
+    VinylSide {
+        field: String,
+        value: String,
+        side: char,
+    },
+}
+
+impl fmt::Display for MetadataWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataWarning::Unparseable { field, value } => {
+                write!(f, "couldn't read {field} \"{value}\"")
+            }
+            MetadataWarning::VinylSide { field, value, side } => {
+                write!(f, "{field} \"{value}\" is on vinyl side {side}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parses_plain_and_padded_numbers() {
+        assert_eq!(Position::parse("3").unwrap().number, Some(3));
+        assert_eq!(Position::parse(" 03 ").unwrap().number, Some(3));
+    }
+
+    #[test]
+    fn splits_combined_number_and_total() {
+        let position = Position::parse("3/12").unwrap();
+        assert_eq!(position.number, Some(3));
+        assert_eq!(position.total, Some(12));
+
+        let position = Position::parse(" 3 / ").unwrap();
+        assert_eq!(position.number, Some(3));
+        assert_eq!(position.total, None);
+    }
+
+    #[test]
+    fn understands_vinyl_sides() {
+        let position = Position::parse("b2").unwrap();
+        assert_eq!(position.number, Some(2));
+        assert_eq!(position.side, Some('B'));
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(Position::parse("two").is_none());
+        assert!(Position::parse("3/x").is_none());
+        assert!(Position::parse("-1").is_none());
+    }
+}
@@ -0,0 +1,426 @@
+// rename.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use getset::Getters;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::error::{MetadataError, Result};
+use crate::field::Field;
+use crate::metadata::MetadataContainer;
+
+/// The template tracks are renamed with unless another is given
+pub const DEFAULT_RENAME_TEMPLATE: &str =
+    "{album_artist}/{year} - {album}/{disc}-{track:02} {title}.{ext}";
+
+/// Characters that can't appear in a file name on common file systems
+const ILLEGAL_CHARACTERS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Why a track can't be moved to the path its template gives
+#[derive(Clone, Debug, Error, PartialEq)]
+pub enum RenameProblem {
+    #[error("{0}")]
+    Template(String),
+
+    #[error("would get the same name as {}", .0.display())]
+    Collision(PathBuf),
+
+    #[error("another file already has that name")]
+    Exists,
+}
+
+/// Where a track would be moved to, or why it can't be
+#[derive(Clone, Debug, Getters, PartialEq)]
+#[get = "pub"]
+pub struct Rename {
+    from: PathBuf,
+    to: std::result::Result<PathBuf, RenameProblem>,
+}
+
+/// Works out where each track goes, relative to `base`, flagging tracks
+/// that would land on each other or on a file that's already there
+pub fn plan_renames(
+    tracks: &[(PathBuf, MetadataContainer)],
+    template: &str,
+    base: &Path,
+) -> Vec<Rename> {
+    let targets: Vec<std::result::Result<PathBuf, RenameProblem>> = tracks
+        .iter()
+        .map(|(path, metadata)| {
+            render_template(template, metadata, path)
+                .map(|target| base.join(target))
+                .map_err(RenameProblem::Template)
+        })
+        .collect();
+
+    // The tracks headed for each path, to find collisions without
+    // comparing every pair, as this runs as the template is typed
+    let mut claimed: HashMap<&PathBuf, Vec<usize>> = HashMap::new();
+    for (i, target) in targets.iter().enumerate() {
+        if let Ok(target) = target {
+            claimed.entry(target).or_default().push(i);
+        }
+    }
+
+    tracks
+        .iter()
+        .zip(&targets)
+        .enumerate()
+        .map(|(i, ((from, _), to))| {
+            let to = to.clone().and_then(|to| {
+                let other = claimed[&to].iter().find(|j| **j != i);
+                if let Some(j) = other {
+                    return Err(RenameProblem::Collision(tracks[*j].0.clone()));
+                }
+                if to != *from && to.exists() && !is_same_file(from, &to) {
+                    return Err(RenameProblem::Exists);
+                }
+                Ok(to)
+            });
+
+            Rename {
+                from: from.clone(),
+                to,
+            }
+        })
+        .collect()
+}
+
+/// Fills the template from the track's fields, giving a relative path.
+/// Fields are written as `{name}`, or `{name:0N}` to pad them with zeros
+/// to N digits, and `{ext}` stands for the file's current extension
+pub fn render_template(
+    template: &str,
+    metadata: &MetadataContainer,
+    path: &Path,
+) -> std::result::Result<PathBuf, String> {
+    let mut rendered = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut placeholder = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+                if !closed {
+                    return Err(format!("{{{placeholder} is never closed"));
+                }
+
+                let (name, format) = match placeholder.split_once(':') {
+                    Some((name, format)) => (name, Some(format)),
+                    None => (placeholder.as_str(), None),
+                };
+
+                let value = field_value(name, metadata, path)
+                    .ok_or_else(|| format!("there's no field called {name}"))?;
+                let value = match format {
+                    Some(format) => pad(&value, format)?,
+                    None => value,
+                };
+                // Values can't add folders of their own
+                rendered.extend(value.chars().map(|c| match c {
+                    '/' | '\\' => '_',
+                    c => c,
+                }));
+            }
+            '}' => return Err("there's a } without a {".to_string()),
+            c => rendered.push(c),
+        }
+    }
+
+    let mut target = PathBuf::new();
+    for component in rendered.split('/') {
+        let component = sanitize(component);
+        if component.is_empty() || component == "." || component == ".." {
+            return Err("the template leaves a folder or file without a name".to_string());
+        }
+        target.push(component);
+    }
+    Ok(target)
+}
+
+/// Picks the folder a template is applied from: the folder holding
+/// every track, less one level for each folder the template creates,
+/// so renaming tracks that already follow the template leaves them be
+pub fn default_rename_base(paths: &[PathBuf], template: &str) -> PathBuf {
+    let mut base = match paths.first().and_then(|path| path.parent()) {
+        Some(parent) => parent.to_path_buf(),
+        None => return PathBuf::new(),
+    };
+    for path in paths {
+        while !path.starts_with(&base) {
+            if !base.pop() {
+                return base;
+            }
+        }
+    }
+
+    for _ in 0..template.matches('/').count() {
+        if !base.pop() {
+            break;
+        }
+    }
+    base
+}
+
+/// Moves the file, creating the folders it goes in if asked to.
+/// An existing file is never overwritten, and files going to
+/// another file system are copied there, then removed
+pub fn rename_file(from: &Path, to: &Path, create_dirs: bool) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        if create_dirs {
+            fs::create_dir_all(parent).map_err(|e| MetadataError::from_io(parent, e))?;
+        }
+    }
+
+    // A new name for the same file, such as a change of case
+    // on a file system that ignores case, has nothing to overwrite
+    if is_same_file(from, to) {
+        return fs::rename(from, to).map_err(|e| MetadataError::from_io(from, e));
+    }
+
+    // Linking fails if the name is taken, unlike renaming, which
+    // would replace a file created since the name was checked
+    match fs::hard_link(from, to) {
+        Ok(()) => fs::remove_file(from).map_err(|e| MetadataError::from_io(from, e)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(name_taken(to)),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => copy_then_remove(from, to),
+        // File systems without hard links are left with checking first,
+        // which can still lose a file created between the check and the rename
+        Err(_) => {
+            if to.exists() {
+                return Err(name_taken(to));
+            }
+            fs::rename(from, to).map_err(|e| MetadataError::from_io(from, e))
+        }
+    }
+}
+
+// Moves a file to another file system. The copy is written to disk
+// before the original is removed, and never replaces an existing file
+fn copy_then_remove(from: &Path, to: &Path) -> Result<()> {
+    let mut target = match OpenOptions::new().write(true).create_new(true).open(to) {
+        Ok(target) => target,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(name_taken(to)),
+        Err(e) => return Err(MetadataError::from_io(to, e)),
+    };
+
+    let copied = File::open(from)
+        .and_then(|mut source| io::copy(&mut source, &mut target))
+        .and_then(|_| target.set_permissions(fs::metadata(from)?.permissions()))
+        .and_then(|_| target.sync_all());
+    if let Err(e) = copied {
+        let _ = fs::remove_file(to);
+        return Err(MetadataError::from_io(to, e));
+    }
+
+    fs::remove_file(from).map_err(|e| MetadataError::from_io(from, e))
+}
+
+fn name_taken(path: &Path) -> MetadataError {
+    MetadataError::from_io(
+        path,
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "another file already has that name",
+        ),
+    )
+}
+
+fn field_value(name: &str, metadata: &MetadataContainer, path: &Path) -> Option<String> {
+    Some(match name {
+        "ext" => path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        "filename" => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        name => {
+            let field = Field::from_name(name)?;
+            let value = match field {
+                // Tracks by a single artist often leave the album artist out
+                Field::AlbumArtist => field.get(metadata).or_else(|| Field::Artist.get(metadata)),
+                field => field.get(metadata),
+            };
+            value.unwrap_or_default()
+        }
+    })
+}
+
+// Pads a value with zeros, as asked for with a format such as `02`
+fn pad(value: &str, format: &str) -> std::result::Result<String, String> {
+    let width = format
+        .strip_prefix('0')
+        .and_then(|width| width.parse::<usize>().ok())
+        .ok_or_else(|| format!("{format} isn't a format, try 02 to pad to two digits"))?;
+    if value.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("{value:0>width$}"))
+}
+
+// Replaces characters file systems reject, and trims
+// the spaces and dots Windows drops from names
+fn sanitize(component: &str) -> String {
+    let replaced: String = component
+        .chars()
+        .map(|c| {
+            if ILLEGAL_CHARACTERS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    replaced.trim().trim_end_matches(['.', ' ']).to_string()
+}
+
+fn is_same_file(first: &Path, second: &Path) -> bool {
+    match (fs::canonicalize(first), fs::canonicalize(second)) {
+        (Ok(first), Ok(second)) => first == second,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tempfile::TempDir;
+
+    fn track() -> MetadataContainer {
+        let mut metadata = MetadataContainer::default();
+        metadata.set_title(Some("What's Up?".to_string()));
+        metadata.set_artist(Some("AC/DC".to_string()));
+        metadata.set_album(Some("Live: 1991".to_string()));
+        metadata.set_year(Some("1992".to_string()));
+        metadata.set_track_number(Some(3));
+        metadata.set_disc_number(Some(1));
+        metadata
+    }
+
+    #[test]
+    fn template_is_filled_and_sanitized() {
+        let target = render_template(DEFAULT_RENAME_TEMPLATE, &track(), Path::new("/in/a.flac"));
+        assert_eq!(
+            target,
+            Ok(PathBuf::from(
+                "AC_DC/1992 - Live_ 1991/1-03 What's Up_.flac"
+            ))
+        );
+
+        assert!(render_template("{nope}", &track(), Path::new("a.mp3")).is_err());
+        assert!(render_template("{title", &track(), Path::new("a.mp3")).is_err());
+        assert!(render_template("{genre}/{title}", &track(), Path::new("a.mp3")).is_err());
+        assert_eq!(
+            render_template("{catalog_number}{title}", &track(), Path::new("a.mp3")),
+            Ok(PathBuf::from("What's Up_"))
+        );
+    }
+
+    #[test]
+    fn collisions_are_flagged() {
+        let base = Path::new("/music");
+        let tracks = vec![
+            (PathBuf::from("/in/a.mp3"), track()),
+            (PathBuf::from("/in/b.mp3"), track()),
+        ];
+        let renames = plan_renames(&tracks, "{title}.{ext}", base);
+        assert_eq!(
+            renames[0].to(),
+            &Err(RenameProblem::Collision(PathBuf::from("/in/b.mp3")))
+        );
+        assert_eq!(
+            renames[1].to(),
+            &Err(RenameProblem::Collision(PathBuf::from("/in/a.mp3")))
+        );
+
+        let renames = plan_renames(&tracks, "{filename} {title}.{ext}", base);
+        assert_eq!(
+            renames[1].to(),
+            &Ok(PathBuf::from("/music/b What's Up_.mp3"))
+        );
+    }
+
+    #[test]
+    fn files_are_moved_without_overwriting() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.path().join("a.mp3");
+        let taken = dir.path().join("taken.mp3");
+        fs::write(&from, b"a").unwrap();
+        fs::write(&taken, b"taken").unwrap();
+
+        assert!(rename_file(&from, &taken, false).is_err());
+        assert_eq!(fs::read(&taken).unwrap(), b"taken");
+
+        let to = dir.path().join("Artist").join("b.mp3");
+        rename_file(&from, &to, true).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read(&to).unwrap(), b"a");
+    }
+
+    #[test]
+    fn files_can_be_copied_across_file_systems() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.path().join("a.mp3");
+        let to = dir.path().join("b.mp3");
+        fs::write(&from, b"a").unwrap();
+
+        copy_then_remove(&from, &to).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read(&to).unwrap(), b"a");
+
+        fs::write(&from, b"new").unwrap();
+        assert!(copy_then_remove(&from, &to).is_err());
+        assert_eq!(fs::read(&from).unwrap(), b"new");
+        assert_eq!(fs::read(&to).unwrap(), b"a");
+    }
+
+    #[test]
+    fn base_leaves_room_for_template_folders() {
+        let paths = vec![
+            PathBuf::from("/music/Artist/Album/01.mp3"),
+            PathBuf::from("/music/Artist/Album/02.mp3"),
+        ];
+        assert_eq!(
+            default_rename_base(&paths, DEFAULT_RENAME_TEMPLATE),
+            PathBuf::from("/music")
+        );
+        assert_eq!(
+            default_rename_base(&paths, "{title}.{ext}"),
+            PathBuf::from("/music/Artist/Album")
+        );
+    }
+}
@@ -0,0 +1,180 @@
+// replace.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use getset::{CopyGetters, Getters};
+use regex::{NoExpand, Regex, RegexBuilder};
+
+use crate::field::Field;
+use crate::metadata::{FieldValue, MetadataContainer, MetadataEdit};
+
+/// A change find and replace would make to one of a track's fields
+#[derive(Clone, Debug, CopyGetters, Getters, PartialEq)]
+pub struct Replacement {
+    #[get_copy = "pub"]
+    field: Field,
+    #[get = "pub"]
+    before: String,
+    #[get = "pub"]
+    after: String,
+}
+
+/// Finds text in tag fields and replaces it
+#[derive(Clone, Debug)]
+pub struct Replacer {
+    regex: Regex,
+    replacement: String,
+    expand: bool,
+}
+
+impl Replacer {
+    /// Finds `find` as plain text, or as a regular expression whose
+    /// capture groups the replacement can use as `$1` or `${name}`
+    pub fn new(
+        find: &str,
+        replacement: &str,
+        use_regex: bool,
+        ignore_case: bool,
+    ) -> Result<Self, String> {
+        if find.is_empty() {
+            return Err("Enter the text to find".to_string());
+        }
+
+        let pattern = if use_regex {
+            find.to_string()
+        } else {
+            regex::escape(find)
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            regex,
+            replacement: replacement.to_string(),
+            expand: use_regex,
+        })
+    }
+
+    /// Returns the text with every match replaced,
+    /// or `None` if that leaves it as it was
+    pub fn replace(&self, text: &str) -> Option<String> {
+        let replaced = if self.expand {
+            self.regex.replace_all(text, self.replacement.as_str())
+        } else {
+            self.regex.replace_all(text, NoExpand(&self.replacement))
+        };
+        (replaced != text).then(|| replaced.into_owned())
+    }
+
+    /// Lists the changes the replacement makes to the track's fields
+    pub fn plan(&self, metadata: &MetadataContainer, fields: &[Field]) -> Vec<Replacement> {
+        plan_changes(metadata, fields, |text| self.replace(text))
+    }
+}
+
+/// Lists the changes `change` makes to the track's text fields,
+/// where it returns `None` for text it leaves as it is. Number
+/// fields are left alone
+pub fn plan_changes<F>(
+    metadata: &MetadataContainer,
+    fields: &[Field],
+    change: F,
+) -> Vec<Replacement>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fields
+        .iter()
+        .filter(|field| !field.is_number())
+        .filter_map(|field| {
+            let before = field.get(metadata)?;
+            let after = change(&before).filter(|after| *after != before)?;
+            Some(Replacement {
+                field: *field,
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Turns a track's replacements into an edit, removing
+/// fields the replacement leaves empty
+pub fn edit_from_replacements(replacements: &[Replacement]) -> MetadataEdit {
+    let mut edit = MetadataEdit::default();
+    for replacement in replacements {
+        let value = if replacement.after.is_empty() {
+            FieldValue::Cleared
+        } else {
+            FieldValue::Set(replacement.after.clone())
+        };
+        replacement
+            .field
+            .set(&mut edit, value)
+            .expect("replacements are only planned for text fields");
+    }
+    edit
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn track() -> MetadataContainer {
+        let mut metadata = MetadataContainer::default();
+        metadata.set_title(Some("Song (ft. Someone)".to_string()));
+        metadata.set_artist(Some("Band FT. Someone".to_string()));
+        metadata.set_album(Some("Album".to_string()));
+        metadata
+    }
+
+    #[test]
+    fn plain_text_is_replaced_literally() {
+        let replacer = Replacer::new("ft.", "feat. $1", false, false).unwrap();
+        let replacements = replacer.plan(&track(), &Field::TEXT);
+        assert_eq!(replacements.len(), 1);
+        assert_eq!(replacements[0].field(), Field::Title);
+        assert_eq!(replacements[0].after(), "Song (feat. $1 Someone)");
+
+        let replacer = Replacer::new("ft.", "feat.", false, true).unwrap();
+        assert_eq!(replacer.plan(&track(), &Field::TEXT).len(), 2);
+        assert_eq!(replacer.plan(&track(), &[Field::Album]), vec![]);
+    }
+
+    #[test]
+    fn regex_capture_groups_are_expanded() {
+        let replacer = Replacer::new(r"\((ft\.) (\w+)\)", "[with $2]", true, false).unwrap();
+        assert_eq!(
+            replacer.replace("Song (ft. Someone)"),
+            Some("Song [with Someone]".to_string())
+        );
+        assert!(Replacer::new("(", "", true, false).is_err());
+        assert!(Replacer::new("", "x", false, false).is_err());
+    }
+
+    #[test]
+    fn emptied_fields_are_cleared() {
+        let replacer = Replacer::new("Album", "", false, false).unwrap();
+        let edit = edit_from_replacements(&replacer.plan(&track(), &Field::TEXT));
+        assert_eq!(edit.album(), &FieldValue::Cleared);
+        assert_eq!(edit.title(), &FieldValue::Unchanged);
+    }
+}
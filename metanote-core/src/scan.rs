@@ -0,0 +1,349 @@
+// scan.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use derive_builder::Builder;
+use getset::{CopyGetters, Setters};
+use mime_guess::MimeGuess;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How far a folder scan should reach
+#[derive(Builder, Clone, Copy, Debug, Default, CopyGetters, PartialEq, Setters)]
+#[get_copy = "pub"]
+#[set = "pub"]
+pub struct ScanOptions {
+    /// How many levels of subfolders to enter, or `None` for no limit
+    #[builder(default)]
+    max_depth: Option<usize>,
+    /// Whether to follow symbolic links to files and folders
+    #[builder(default)]
+    follow_symlinks: bool,
+}
+
+/// The file names an album's art is usually kept under, in order of preference
+pub const FOLDER_ART_PATTERNS: [&str; 4] = ["cover.*", "folder.*", "front.*", "album.*"];
+
+/// A file found while scanning, or a path that couldn't be read
+pub type ScanEntry = Result<PathBuf, (PathBuf, io::Error)>;
+
+/// Returns the audio files in `dir` and its subfolders, sorted by path.
+/// Subfolders that can't be read are skipped. Stops early, returning
+/// what was found so far, once `cancelled` is set
+pub fn find_audio_files(
+    dir: &Path,
+    options: &ScanOptions,
+    cancelled: &AtomicBool,
+) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    walk_dir(dir, options, cancelled, &mut |path| files.push(path))?;
+    Ok(files)
+}
+
+/// Expands a mix of files and folders into the audio files to open,
+/// handing each to `visit` as soon as it's found. Files are kept as given,
+/// so their format is checked when they're read, while folders are walked
+/// in path order as `find_audio_files` does. Paths that can't be read are
+/// handed over as errors. Stops early once `cancelled` is set
+pub fn visit_audio_files(
+    paths: &[PathBuf],
+    options: &ScanOptions,
+    cancelled: &AtomicBool,
+    mut visit: impl FnMut(ScanEntry),
+) {
+    for path in paths {
+        if cancelled.load(Ordering::Relaxed) {
+            break;
+        }
+
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => {
+                let walked = walk_dir(path, options, cancelled, &mut |file| visit(Ok(file)));
+                if let Err(e) = walked {
+                    visit(Err((path.clone(), e)));
+                }
+            }
+            Ok(_) => visit(Ok(path.clone())),
+            Err(e) => visit(Err((path.clone(), e))),
+        }
+    }
+}
+
+/// Returns the image in `dir` matching the earliest of the given file name
+/// patterns. Patterns ignore case, and `*` stands for any run of characters
+pub fn find_folder_art(dir: &Path, patterns: &[String]) -> io::Result<Option<PathBuf>> {
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_image_file(path))
+        .collect();
+    images.sort();
+
+    for pattern in patterns {
+        let pattern = pattern.to_lowercase();
+        let found = images.iter().find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| matches_pattern(&name.to_lowercase(), &pattern))
+        });
+        if let Some(found) = found {
+            return Ok(Some(found.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+fn walk_dir<F: FnMut(PathBuf)>(
+    dir: &Path,
+    options: &ScanOptions,
+    cancelled: &AtomicBool,
+    found: &mut F,
+) -> io::Result<()> {
+    let mut visited = HashSet::new();
+    visited.insert(fs::canonicalize(dir)?);
+    scan_dir(dir, 0, options, cancelled, &mut visited, found)
+}
+
+fn scan_dir<F: FnMut(PathBuf)>(
+    dir: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    cancelled: &AtomicBool,
+    visited: &mut HashSet<PathBuf>,
+    found: &mut F,
+) -> io::Result<()> {
+    // Each folder's entries are sorted, so files are found in path order
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(e) => log::warn!("skipping an entry of {}, {e}", dir.display()),
+        }
+    }
+    paths.sort();
+
+    for path in paths {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let link_metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log::warn!("skipping {}, {e}", path.display());
+                continue;
+            }
+        };
+        if link_metadata.file_type().is_symlink() && !options.follow_symlinks() {
+            continue;
+        }
+
+        // Follows the link, if there is one
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log::warn!("skipping {}, {e}", path.display());
+                continue;
+            }
+        };
+
+        if metadata.is_dir() {
+            if options.max_depth().is_some_and(|max| depth >= max) {
+                continue;
+            }
+
+            // Linked folders could lead back to one already scanned
+            let canonical = match fs::canonicalize(&path) {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    log::warn!("skipping folder {}, {e}", path.display());
+                    continue;
+                }
+            };
+            if !visited.insert(canonical) {
+                continue;
+            }
+
+            if let Err(e) = scan_dir(&path, depth + 1, options, cancelled, visited, found) {
+                log::warn!("skipping folder {}, {e}", path.display());
+            }
+        } else if is_audio_file(&path) {
+            found(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_audio_file(path: &Path) -> bool {
+    MimeGuess::from_path(path)
+        .iter()
+        .any(|mime| mime.type_() == mime_guess::mime::AUDIO)
+}
+
+fn is_image_file(path: &Path) -> bool {
+    MimeGuess::from_path(path)
+        .iter()
+        .any(|mime| mime.type_() == mime_guess::mime::IMAGE)
+}
+
+// Matches `name` against a pattern where `*` stands for any run of characters
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let mut rest = match name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    match parts.split_last() {
+        // No wildcard, so the whole name had to match
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.ends_with(last)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn respects_max_depth() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        let nested = root.join("Artist").join("Album");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join("top.mp3"), b"").unwrap();
+        fs::write(root.join("notes.txt"), b"").unwrap();
+        fs::write(nested.join("01 track.flac"), b"").unwrap();
+
+        let not_cancelled = AtomicBool::new(false);
+
+        let top_only = ScanOptionsBuilder::default()
+            .max_depth(Some(0))
+            .build()
+            .unwrap();
+        let found = find_audio_files(&root, &top_only, &not_cancelled).unwrap();
+        assert_eq!(found, vec![root.join("top.mp3")]);
+
+        let found = find_audio_files(&root, &ScanOptions::default(), &not_cancelled).unwrap();
+        assert_eq!(
+            found,
+            vec![nested.join("01 track.flac"), root.join("top.mp3")]
+        );
+    }
+
+    #[test]
+    fn collects_files_and_folders() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        let album = root.join("Album");
+        fs::create_dir_all(&album).unwrap();
+        fs::write(root.join("single.ogg"), b"").unwrap();
+        fs::write(album.join("01 track.mp3"), b"").unwrap();
+
+        let paths = vec![
+            root.join("single.ogg"),
+            album.clone(),
+            root.join("missing.mp3"),
+        ];
+        let mut files = Vec::new();
+        let mut failures = Vec::new();
+        visit_audio_files(
+            &paths,
+            &ScanOptions::default(),
+            &AtomicBool::new(false),
+            |entry| match entry {
+                Ok(file) => files.push(file),
+                Err(failure) => failures.push(failure),
+            },
+        );
+        assert_eq!(
+            files,
+            vec![root.join("single.ogg"), album.join("01 track.mp3")]
+        );
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, root.join("missing.mp3"));
+    }
+
+    #[test]
+    fn stops_walking_once_cancelled() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        for name in ["a.mp3", "b.mp3", "c.mp3"] {
+            fs::write(root.join(name), b"").unwrap();
+        }
+
+        let cancelled = AtomicBool::new(false);
+        let mut files = Vec::new();
+        let paths = [dir.path().to_path_buf()];
+        visit_audio_files(&paths, &ScanOptions::default(), &cancelled, |entry| {
+            files.push(entry.unwrap());
+            cancelled.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(files, vec![root.join("a.mp3")]);
+    }
+
+    #[test]
+    fn folder_art_follows_pattern_order() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().to_path_buf();
+        fs::write(root.join("01 track.mp3"), b"").unwrap();
+        fs::write(root.join("Folder.JPG"), b"").unwrap();
+        fs::write(root.join("front.png"), b"").unwrap();
+        fs::write(root.join("cover.txt"), b"").unwrap();
+
+        let patterns =
+            |patterns: &[&str]| -> Vec<String> { patterns.iter().map(|p| p.to_string()).collect() };
+        assert_eq!(
+            find_folder_art(&root, &patterns(&FOLDER_ART_PATTERNS)).unwrap(),
+            Some(root.join("Folder.JPG"))
+        );
+        assert_eq!(
+            find_folder_art(&root, &patterns(&["front.*", "folder.*"])).unwrap(),
+            Some(root.join("front.png"))
+        );
+        assert_eq!(
+            find_folder_art(&root, &patterns(&["cover.*"])).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn patterns_match_wildcards() {
+        assert!(matches_pattern("cover.jpg", "cover.*"));
+        assert!(matches_pattern("cover.jpg", "cover.jpg"));
+        assert!(matches_pattern("album cover (front).png", "*cover*.png"));
+        assert!(!matches_pattern("cover.jpg.bak", "*.jpg"));
+        assert!(!matches_pattern("back.jpg", "cover.*"));
+    }
+}
@@ -47,10 +47,20 @@ mod imp {
 
     impl ObjectImpl for MetanoteApplication {}
     impl ApplicationImpl for MetanoteApplication {
-        fn activate(&self, application: &Self::Type) {
+        fn startup(&self, application: &Self::Type) {
+            self.parent_startup(application);
             application.setup_actions();
-            let window = MetanoteApplicationWindow::new(application);
+        }
+
+        fn activate(&self, application: &Self::Type) {
+            application.main_window().present();
+        }
+
+        // Files given on the command line or from "Open With"
+        fn open(&self, application: &Self::Type, files: &[gio::File], _hint: &str) {
+            let window = application.main_window();
             window.present();
+            window.confirm_open(files.to_vec());
         }
     }
     impl GtkApplicationImpl for MetanoteApplication {}
@@ -78,7 +88,7 @@ impl MetanoteApplication {
     pub fn new() -> Self {
         glib::Object::new(&[
             ("application-id", &APP_ID),
-            ("flags", &gio::ApplicationFlags::empty()),
+            ("flags", &gio::ApplicationFlags::HANDLES_OPEN),
         ])
         .expect("Failed to create MetanoteApplication")
     }
@@ -91,6 +101,17 @@ impl MetanoteApplication {
                 app.show_about();
             })
         );
+
+        self.set_accels_for_action("win.undo", &["<primary>z"]);
+        self.set_accels_for_action("win.redo", &["<primary><shift>z"]);
+        self.set_accels_for_action("win.find-replace", &["<primary>h"]);
+    }
+
+    // Reuses the open window, so there's only ever one
+    fn main_window(&self) -> MetanoteApplicationWindow {
+        self.active_window()
+            .and_then(|w| w.downcast::<MetanoteApplicationWindow>().ok())
+            .unwrap_or_else(|| MetanoteApplicationWindow::new(self))
     }
 
     fn show_about(&self) {
@@ -27,17 +27,19 @@ use gtk::{Button, FileChooserAction, FileChooserNative, ResponseType};
 
 use std::cell::RefCell;
 use std::ops::Deref;
-use std::path::PathBuf;
+
+use metanote_core::metadata::Art;
 
 use crate::editor_page::MetanoteEditorPage;
-use crate::metadata::Art;
+use crate::picture::ToPictureWidget;
 
 mod imp {
     use super::*;
 
     pub struct ArtButton {
         pub file_chooser: FileChooserNative,
-        pub path: RefCell<Option<PathBuf>>,
+        // The image shown, or `None` for a button waiting for one
+        pub art: RefCell<Option<Art>>,
         pub notifiable: RefCell<Option<Value>>,
     }
 
@@ -56,7 +58,7 @@ mod imp {
 
             Self {
                 file_chooser,
-                path: Default::default(),
+                art: Default::default(),
                 notifiable: RefCell::new(None),
             }
         }
@@ -113,6 +115,7 @@ impl ArtButton {
         let picture = art.to_picture_widget();
         let button: ArtButton =
             Object::new(&[("child", &picture)]).expect("failed to create `ArtButton`.");
+        button.imp().art.replace(Some(art.clone()));
 
         if let Some(notifiable) = notifiable {
             button
@@ -138,10 +141,35 @@ impl ArtButton {
         self.imp().file_chooser.connect_response(
             clone!(@weak self as button => move |fc, response| {
                 if response == ResponseType::Accept {
-                    let path = &fc.file().unwrap().path().unwrap();
-                    let art = Art::from_path(path).unwrap();
-                    button.imp().path.replace(Some(path.to_path_buf()));
+                    let path = match fc.file().and_then(|f| f.path()) {
+                        Some(path) => path,
+                        None => return,
+                    };
+                    // The image shown is left as it was
+                    let mut art = match Art::from_path(&path) {
+                        Ok(art) => art,
+                        Err(e) => {
+                            if let Some(notifiable) = button.imp().notifiable.borrow().deref() {
+                                let notifiable = notifiable.get::<MetanoteEditorPage>().expect("failed to get editor page");
+                                notifiable.on_art_error(&format!("Couldn't use the image, {e}"));
+                            }
+                            return;
+                        }
+                    };
+
+                    // The image is reworked as the editor's art options ask
+                    if let Some(notifiable) = button.imp().notifiable.borrow().deref() {
+                        let notifiable = notifiable.get::<MetanoteEditorPage>().expect("failed to get editor page");
+                        art = notifiable.process_art(art);
+                    }
+
+                    // A replaced image keeps the type and description it had
+                    if let Some(old_art) = button.imp().art.borrow().as_ref() {
+                        art.set_pic_type(*old_art.pic_type());
+                        art.set_description(old_art.description().clone());
+                    }
                     button.set_child(Some(&art.to_picture_widget()));
+                    button.imp().art.replace(Some(art));
 
                     if let Some(notifiable) =  button.imp().notifiable.borrow().deref() {
                         // Due to limitations of Value, a concrete type must be specified
@@ -168,4 +196,8 @@ impl Default for ArtButton {
 /// art changes
 pub trait ArtButtonChangeNotifiable {
     fn on_art_change(&self);
+    /// Reworks an image chosen through the button before it's shown
+    fn process_art(&self, art: Art) -> Art;
+    /// Called with a message when an image can't be used
+    fn on_art_error(&self, message: &str);
 }
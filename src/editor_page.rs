@@ -22,18 +22,37 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gtk::subclass::prelude::*;
 
-use adw::{Carousel, PreferencesGroup};
-use anyhow::Result;
+use adw::{ActionRow, Carousel, ExpanderRow, PreferencesGroup};
 use gtk::glib;
+use gtk::glib::clone;
 use gtk::glib::subclass::InitializingObject;
-use gtk::{Box, CompositeTemplate, Entry, Widget};
-use std::cell::RefCell;
-use std::ops::Deref;
+use gtk::{gdk, gio};
+use gtk::{
+    Align, Box, Button, CompositeTemplate, DropDown, Entry, FileChooserAction, FileChooserNative,
+    FileFilter, ResponseType, StringList, Widget,
+};
+use gtk_macros::action;
+use std::cell::{Cell, RefCell};
+
+use metanote_core::artwork::ArtOptions;
+use metanote_core::error::MetadataError;
+use metanote_core::field::ExtraField;
+use metanote_core::metadata::{
+    picture_type_name, Art, FieldValue, MetadataContainer, MetadataEdit, MetadataWriteCapable,
+    TagType, PICTURE_TYPES,
+};
 
 use crate::art_button::{ArtButton, ArtButtonChangeNotifiable};
-use crate::metadata::{Art, MetadataContainer, MetadataWriteCapable};
+use crate::history::{History, Operation, SavedTrack};
 use crate::row::MetanoteRow;
 
+/// Each track a save touched, along with whether it was written
+pub type SaveReport = Vec<(MetanoteRow, Result<(), MetadataError>)>;
+
+// `Box` here is the widget, so the standard one is spelled out
+type ChangeHandler = std::boxed::Box<dyn Fn(&MetanoteEditorPage)>;
+type ErrorHandler = std::boxed::Box<dyn Fn(&MetanoteEditorPage, &str)>;
+
 mod imp {
 
     use super::*;
@@ -41,9 +60,28 @@ mod imp {
     #[derive(CompositeTemplate, Default)]
     #[template(resource = "/com/gitlab/bmreading/Metanote/editor_page.ui")]
     pub struct MetanoteEditorPage {
+        pub art_file_chooser: FileChooserNative,
+        pub art_save_chooser: FileChooserNative,
+        // The "art" actions behind the image options menu
+        pub art_actions: gio::SimpleActionGroup,
+
         #[template_child]
         pub art_carousel: TemplateChild<Carousel>,
         #[template_child]
+        pub art_add_button: TemplateChild<Button>,
+        #[template_child]
+        pub art_remove_button: TemplateChild<Button>,
+        #[template_child]
+        pub art_move_back_button: TemplateChild<Button>,
+        #[template_child]
+        pub art_move_forward_button: TemplateChild<Button>,
+        #[template_child]
+        pub art_clear_button: TemplateChild<Button>,
+        #[template_child]
+        pub art_type_dropdown: TemplateChild<DropDown>,
+        #[template_child]
+        pub art_description_text: TemplateChild<Entry>,
+        #[template_child]
         pub tag_row_group: TemplateChild<PreferencesGroup>,
         #[template_child]
         pub title_text: TemplateChild<Entry>,
@@ -71,9 +109,34 @@ mod imp {
         pub copyright_text: TemplateChild<Entry>,
         #[template_child]
         pub comment_text: TemplateChild<Entry>,
+        #[template_child]
+        pub advanced_row: TemplateChild<ExpanderRow>,
+        // Entries for the extra fields, built when the page is constructed
+        pub extra_entries: RefCell<Vec<(ExtraField, Entry)>>,
+        #[template_child]
+        pub custom_row: TemplateChild<ExpanderRow>,
+        #[template_child]
+        pub custom_key_text: TemplateChild<Entry>,
+        #[template_child]
+        pub custom_value_text: TemplateChild<Entry>,
+        #[template_child]
+        pub custom_add_button: TemplateChild<Button>,
+        // Rows for the custom items shown, rebuilt with the metadata
+        pub custom_entries: RefCell<Vec<(String, Entry, ActionRow)>>,
+
+        // How images are reworked as they're added
+        pub art_options: Cell<ArtOptions>,
 
         pub metanote_rows: RefCell<Vec<MetanoteRow>>,
-        pub metadata: RefCell<MetadataContainer>,
+        pub metadata: RefCell<MetadataEdit>,
+        // The rows' metadata as it was merged, before any edits
+        pub baseline: RefCell<MetadataEdit>,
+        pub history: RefCell<History>,
+        // Set while entries are filled in programmatically,
+        // so those changes aren't recorded as edits
+        pub loading: Cell<bool>,
+        pub change_handlers: RefCell<Vec<ChangeHandler>>,
+        pub error_handlers: RefCell<Vec<ErrorHandler>>,
     }
 
     #[glib::object_subclass]
@@ -83,8 +146,33 @@ mod imp {
         type ParentType = Box;
 
         fn new() -> Self {
+            let image_filter = FileFilter::new();
+            image_filter.set_name(Some("Images"));
+            image_filter.add_mime_type("image/*");
+            let art_file_chooser = FileChooserNative::builder()
+                .modal(true)
+                .action(FileChooserAction::Open)
+                .title("Choose an image")
+                .filter(&image_filter)
+                .build();
+            let art_save_chooser = FileChooserNative::builder()
+                .modal(true)
+                .action(FileChooserAction::Save)
+                .title("Save image as")
+                .build();
+
             Self {
+                art_file_chooser,
+                art_save_chooser,
+                art_actions: Default::default(),
                 art_carousel: TemplateChild::default(),
+                art_add_button: TemplateChild::default(),
+                art_remove_button: TemplateChild::default(),
+                art_move_back_button: TemplateChild::default(),
+                art_move_forward_button: TemplateChild::default(),
+                art_clear_button: TemplateChild::default(),
+                art_type_dropdown: TemplateChild::default(),
+                art_description_text: TemplateChild::default(),
                 tag_row_group: TemplateChild::default(),
                 title_text: TemplateChild::default(),
                 artist_text: TemplateChild::default(),
@@ -99,8 +187,21 @@ mod imp {
                 composer_text: TemplateChild::default(),
                 copyright_text: TemplateChild::default(),
                 comment_text: TemplateChild::default(),
+                advanced_row: TemplateChild::default(),
+                extra_entries: Default::default(),
+                custom_row: TemplateChild::default(),
+                custom_key_text: TemplateChild::default(),
+                custom_value_text: TemplateChild::default(),
+                custom_add_button: TemplateChild::default(),
+                custom_entries: Default::default(),
+                art_options: Default::default(),
                 metanote_rows: Default::default(),
                 metadata: Default::default(),
+                baseline: Default::default(),
+                history: Default::default(),
+                loading: Default::default(),
+                change_handlers: Default::default(),
+                error_handlers: Default::default(),
             }
         }
 
@@ -113,7 +214,12 @@ mod imp {
         }
     }
 
-    impl ObjectImpl for MetanoteEditorPage {}
+    impl ObjectImpl for MetanoteEditorPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup_callbacks();
+        }
+    }
     impl WidgetImpl for MetanoteEditorPage {}
     impl BoxImpl for MetanoteEditorPage {}
 }
@@ -135,38 +241,706 @@ impl MetanoteEditorPage {
         glib::Object::new(&[]).expect("failed to create MetanoteEditorPage")
     }
 
+    fn setup_callbacks(&self) {
+        self.add_extra_rows();
+        self.setup_art_details();
+        self.setup_art_controls();
+        self.setup_art_actions();
+
+        for (field, entry) in self.entries() {
+            entry.connect_changed(clone!(@weak self as page => move |_| {
+                page.on_field_changed(field);
+            }));
+        }
+
+        for (field, entry) in self.imp().extra_entries.borrow().iter() {
+            let name = field.name();
+            entry.connect_changed(clone!(@weak self as page => move |_| {
+                page.on_field_changed(name);
+            }));
+        }
+
+        self.imp()
+            .custom_add_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                let imp = page.imp();
+                let key = imp.custom_key_text.text().trim().to_string();
+                if key.is_empty() {
+                    return;
+                }
+
+                page.add_custom_item(key, imp.custom_value_text.text().to_string());
+                imp.custom_key_text.set_text("");
+                imp.custom_value_text.set_text("");
+            }));
+    }
+
+    // The picture type and description of the image in view
+    fn setup_art_details(&self) {
+        let imp = self.imp();
+        let type_names: Vec<&str> = PICTURE_TYPES
+            .iter()
+            .map(|t| picture_type_name(*t))
+            .collect();
+        imp.art_type_dropdown
+            .set_model(Some(&StringList::new(&type_names)));
+
+        imp.art_carousel
+            .connect_page_changed(clone!(@weak self as page => move |_, _| {
+                page.show_art_details();
+            }));
+        imp.art_type_dropdown
+            .connect_selected_notify(clone!(@weak self as page => move |_| {
+                page.on_art_details_changed("art_type", false);
+            }));
+        imp.art_description_text
+            .connect_changed(clone!(@weak self as page => move |_| {
+                page.on_art_details_changed("art_description", true);
+            }));
+    }
+
+    fn setup_art_controls(&self) {
+        let imp = self.imp();
+
+        imp.art_add_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                let window = page
+                    .root()
+                    .expect("failed to get editor page's root")
+                    .downcast::<gtk::ApplicationWindow>()
+                    .expect("failed to get editor page's application window");
+                page.imp().art_file_chooser.set_transient_for(Some(&window));
+                page.imp().art_file_chooser.show();
+            }));
+
+        imp.art_file_chooser
+            .connect_response(clone!(@weak self as page => move |fc, response| {
+                if response != ResponseType::Accept {
+                    return;
+                }
+                let path = match fc.file().and_then(|f| f.path()) {
+                    Some(path) => path,
+                    None => return,
+                };
+
+                match Art::from_path(&path) {
+                    Ok(art) => page.insert_art(art),
+                    Err(e) => page.report_error(&format!("Couldn't add the image, {e}")),
+                }
+            }));
+
+        imp.art_remove_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                page.edit_art(|art, index| {
+                    if index < art.len() {
+                        art.remove(index);
+                    }
+                    index.min(art.len().saturating_sub(1))
+                });
+            }));
+
+        imp.art_move_back_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                page.edit_art(|art, index| {
+                    if index == 0 || index >= art.len() {
+                        return index;
+                    }
+                    art.swap(index, index - 1);
+                    index - 1
+                });
+            }));
+
+        imp.art_move_forward_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                page.edit_art(|art, index| {
+                    if index + 1 >= art.len() {
+                        return index;
+                    }
+                    art.swap(index, index + 1);
+                    index + 1
+                });
+            }));
+
+        imp.art_clear_button
+            .connect_clicked(clone!(@weak self as page => move |_| {
+                page.edit_art(|art, _| {
+                    art.clear();
+                    0
+                });
+            }));
+    }
+
+    fn setup_art_actions(&self) {
+        let imp = self.imp();
+
+        action!(
+            imp.art_actions,
+            "save",
+            clone!(@weak self as page => move |_, _| {
+                page.show_art_save_chooser();
+            })
+        );
+
+        action!(
+            imp.art_actions,
+            "copy",
+            clone!(@weak self as page => move |_, _| {
+                page.copy_art();
+            })
+        );
+
+        action!(
+            imp.art_actions,
+            "paste",
+            clone!(@weak self as page => move |_, _| {
+                page.paste_art();
+            })
+        );
+
+        imp.art_save_chooser
+            .connect_response(clone!(@weak self as page => move |fc, response| {
+                if response != ResponseType::Accept {
+                    return;
+                }
+                let path = match fc.file().and_then(|f| f.path()) {
+                    Some(path) => path,
+                    None => return,
+                };
+
+                if let Some(art) = page.current_art() {
+                    if let Err(e) = art.save_to_path(&path) {
+                        page.report_error(&format!("Couldn't save the image, {e}"));
+                    }
+                }
+            }));
+
+        self.insert_action_group("art", Some(&imp.art_actions));
+    }
+
+    // Less-used fields are listed under the advanced expander
+    fn add_extra_rows(&self) {
+        let imp = self.imp();
+        let mut extra_entries = imp.extra_entries.borrow_mut();
+
+        for field in ExtraField::ALL {
+            let entry = Entry::builder().valign(Align::Center).build();
+            let row = ActionRow::builder().title(field.label()).build();
+            row.add_suffix(&entry);
+            imp.advanced_row.add_row(&row);
+            extra_entries.push((field, entry));
+        }
+    }
+
+    // Each entry paired with the name of the field it edits
+    fn entries(&self) -> [(&'static str, Entry); 13] {
+        let imp = self.imp();
+        [
+            ("title", imp.title_text.get()),
+            ("artist", imp.artist_text.get()),
+            ("album_artist", imp.album_artist_text.get()),
+            ("album", imp.album_text.get()),
+            ("track_number", imp.track_number_text.get()),
+            ("track_total", imp.track_total_text.get()),
+            ("genre", imp.genre_text.get()),
+            ("year", imp.year_text.get()),
+            ("disc_number", imp.disc_number_text.get()),
+            ("disc_total", imp.disc_total_text.get()),
+            ("composer", imp.composer_text.get()),
+            ("copyright", imp.copyright_text.get()),
+            ("comment", imp.comment_text.get()),
+        ]
+    }
+
+    /// Calls `f` whenever the edits pending in the editor may have changed
+    pub fn connect_changed<F: Fn(&Self) + 'static>(&self, f: F) {
+        self.imp()
+            .change_handlers
+            .borrow_mut()
+            .push(std::boxed::Box::new(f));
+    }
+
+    fn notify_changed(&self) {
+        for handler in self.imp().change_handlers.borrow().iter() {
+            handler(self);
+        }
+    }
+
+    /// Calls `f` with a message whenever something the user asked for fails
+    pub fn connect_error<F: Fn(&Self, &str) + 'static>(&self, f: F) {
+        self.imp()
+            .error_handlers
+            .borrow_mut()
+            .push(std::boxed::Box::new(f));
+    }
+
+    fn report_error(&self, message: &str) {
+        log::warn!("{message}");
+        for handler in self.imp().error_handlers.borrow().iter() {
+            handler(self, message);
+        }
+    }
+
+    /// Switches the editor to other rows. Edits made to the
+    /// previous rows are kept on those rows until saved
     pub fn set_metadata(&self, rows: &[MetanoteRow]) {
+        self.commit();
+
         let imp = self.imp();
         imp.metanote_rows.replace(rows.to_vec());
+        imp.history.borrow_mut().forget_edits();
+        self.reload();
+        self.notify_changed();
+    }
+
+    /// Applies the edits made in the editor to the rows it has
+    pub fn commit(&self) {
+        self.update_metadata();
+        let imp = self.imp();
+        for row in imp.metanote_rows.borrow().iter() {
+            row.replace_metadata(&imp.metadata.borrow());
+        }
+        imp.baseline.replace(self.merged_metadata());
+    }
+
+    /// Runs `change` on the given rows, recording whatever it
+    /// changes as one step that undo reverses for every row
+    pub fn change_rows(&self, rows: &[MetanoteRow], change: impl FnOnce()) {
+        let before: Vec<MetadataContainer> = rows
+            .iter()
+            .map(|row| row.imp().metadata.borrow().clone())
+            .collect();
+        change();
+
+        let tracks: Vec<SavedTrack> = rows
+            .iter()
+            .zip(before)
+            .filter_map(|(row, before)| {
+                let after = row.imp().metadata.borrow().clone();
+                (before != after).then(|| SavedTrack {
+                    row: row.clone(),
+                    before,
+                    after,
+                })
+            })
+            .collect();
+        if !tracks.is_empty() {
+            self.imp()
+                .history
+                .borrow_mut()
+                .record(Operation::Batch { tracks });
+        }
+    }
+
+    /// Returns true if the editor holds edits not yet applied to its rows
+    pub fn has_pending_edits(&self) -> bool {
+        let imp = self.imp();
+        let edit = imp.metadata.borrow();
+        imp.metanote_rows.borrow().iter().any(|row| {
+            let current = row.imp().metadata.borrow();
+            current.apply(&edit) != *current
+        })
+    }
+
+    /// Throws away the edits pending in the editor,
+    /// showing its rows' metadata again
+    pub fn discard_edits(&self) {
+        self.reload();
+        self.notify_changed();
+    }
+
+    // Shows the merged metadata of the rows the editor has
+    fn reload(&self) {
+        let imp = self.imp();
+        if imp.metanote_rows.borrow().len() > 0 {
+            let merged = self.merged_metadata();
+            imp.baseline.replace(merged.clone());
+            self.show_metadata(merged);
+            self.show_held_fields();
+        }
+    }
 
-        if rows.len() > 0 {
-            // Consolidate metadata into vec
-            let mut metadata_containers = Vec::new();
-            for row in imp.metanote_rows.borrow().iter() {
-                metadata_containers.push(row.imp().metadata.borrow().clone());
+    // Hides the advanced fields that some row's tags have no item for,
+    // as saving a value there would fail
+    fn show_held_fields(&self) {
+        let imp = self.imp();
+        let tag_types: Vec<Vec<TagType>> = imp
+            .metanote_rows
+            .borrow()
+            .iter()
+            .map(|row| {
+                let metadata = row.imp().metadata.borrow();
+                match metadata.write_to().is_empty() {
+                    true => metadata.tag_type().iter().copied().collect(),
+                    false => metadata.write_to().clone(),
+                }
+            })
+            .collect();
+
+        for (field, entry) in imp.extra_entries.borrow().iter() {
+            let held = tag_types
+                .iter()
+                .all(|types| types.iter().any(|tag_type| field.held_by(*tag_type)));
+            if let Some(row) = entry.ancestor(ActionRow::static_type()) {
+                row.set_visible(held);
             }
+        }
+    }
+
+    // Merges like metadata fields to determine appropriate metadata to operate on
+    fn merged_metadata(&self) -> MetadataEdit {
+        let metadata_containers: Vec<MetadataContainer> = self
+            .imp()
+            .metanote_rows
+            .borrow()
+            .iter()
+            .map(|row| row.imp().metadata.borrow().clone())
+            .collect();
+        MetadataContainer::merge(&metadata_containers)
+    }
+
+    fn show_metadata(&self, metadata: MetadataEdit) {
+        let imp = self.imp();
+        imp.loading.set(true);
+        self.set_artwork(&metadata);
+        self.set_textual_tags(&metadata);
+        imp.metadata.replace(metadata);
+        self.show_art_details();
+        imp.loading.set(false);
+    }
+
+    fn on_field_changed(&self, field: &'static str) {
+        let imp = self.imp();
+        if imp.loading.get() {
+            return;
+        }
+
+        let before = imp.metadata.borrow().clone();
+        self.update_metadata();
+        let after = imp.metadata.borrow().clone();
 
-            // Merge like metadata fields to determine appropriate metadata to operate on
-            let merged = MetadataContainer::merge(&metadata_containers);
-            self.set_artwork(&merged);
-            self.set_textual_tags(&merged);
-            imp.metadata.replace(merged);
+        if before != after {
+            imp.history.borrow_mut().record(Operation::Edit {
+                field,
+                coalesce: true,
+                before,
+                after,
+            });
+        }
+        self.notify_changed();
+    }
+
+    /// Reverses the last change. Returns a report if
+    /// files had to be rewritten to do so
+    pub fn undo<T: MetadataWriteCapable>(&self, metadata_agent: &T) -> Option<SaveReport> {
+        let operation = self.imp().history.borrow_mut().undo()?;
+        let report = self.apply_operation(operation, true, metadata_agent);
+        self.notify_changed();
+        report
+    }
+
+    /// Reapplies the last undone change. Returns a report
+    /// if files had to be rewritten to do so
+    pub fn redo<T: MetadataWriteCapable>(&self, metadata_agent: &T) -> Option<SaveReport> {
+        let operation = self.imp().history.borrow_mut().redo()?;
+        let report = self.apply_operation(operation, false, metadata_agent);
+        self.notify_changed();
+        report
+    }
+
+    fn apply_operation<T: MetadataWriteCapable>(
+        &self,
+        operation: Operation,
+        reverse: bool,
+        metadata_agent: &T,
+    ) -> Option<SaveReport> {
+        match operation {
+            Operation::Edit { before, after, .. } => {
+                self.show_metadata(if reverse { before } else { after });
+                None
+            }
+            Operation::Save { tracks } => {
+                let mut report = Vec::new();
+                for track in tracks {
+                    let metadata = if reverse { track.before } else { track.after };
+                    track.row.set_metadata(metadata);
+                    let result = track.row.write_metadata(metadata_agent);
+                    track.row.set_save_result(&result);
+                    report.push((track.row, result));
+                }
+                self.reload();
+                Some(report)
+            }
+            Operation::Batch { tracks } => {
+                for track in tracks {
+                    track
+                        .row
+                        .set_metadata(if reverse { track.before } else { track.after });
+                }
+                self.reload();
+                None
+            }
         }
     }
 
-    fn set_artwork(&self, metadata: &MetadataContainer) {
+    fn set_artwork(&self, metadata: &MetadataEdit) {
         self.clear_art_carousel();
-        if let Some(art) = metadata.art() {
+        if let FieldValue::Set(art) = metadata.art() {
             for art_element in art {
                 self.imp()
                     .art_carousel
-                    .append(&ArtButton::with_art(&art_element, Some(self)));
+                    .append(&ArtButton::with_art(art_element, Some(self)));
             }
         } else {
             self.imp().art_carousel.append(&ArtButton::new(Some(self)));
         }
     }
 
+    // The image in view, if the tracks share their art
+    fn current_art_index(&self) -> Option<usize> {
+        let art_carousel = &self.imp().art_carousel;
+        if art_carousel.n_pages() == 0 {
+            return None;
+        }
+        Some(art_carousel.position().round() as usize)
+    }
+
+    // The image in view, unless the tracks' art differs
+    fn current_art(&self) -> Option<Art> {
+        match (self.imp().metadata.borrow().art(), self.current_art_index()) {
+            (FieldValue::Set(art), Some(index)) => art.get(index).cloned(),
+            _ => None,
+        }
+    }
+
+    fn show_art_details(&self) {
+        let imp = self.imp();
+        let art = self.current_art();
+
+        set_action_enabled(&imp.art_actions, "save", art.is_some());
+        set_action_enabled(&imp.art_actions, "copy", art.is_some());
+
+        let was_loading = imp.loading.replace(true);
+        imp.art_type_dropdown.set_sensitive(art.is_some());
+        imp.art_description_text.set_sensitive(art.is_some());
+        imp.art_remove_button.set_sensitive(art.is_some());
+        imp.art_move_back_button.set_sensitive(art.is_some());
+        imp.art_move_forward_button.set_sensitive(art.is_some());
+        match art {
+            Some(art) => {
+                let position = PICTURE_TYPES
+                    .iter()
+                    .position(|t| t == art.pic_type())
+                    .unwrap_or(0);
+                imp.art_type_dropdown.set_selected(position as u32);
+                imp.art_description_text
+                    .set_text(art.description().as_deref().unwrap_or(""));
+            }
+            None => imp.art_description_text.set_text(""),
+        }
+        imp.loading.set(was_loading);
+    }
+
+    // `coalesce` is set for typing, which is undone as a whole
+    fn on_art_details_changed(&self, field: &'static str, coalesce: bool) {
+        let imp = self.imp();
+        if imp.loading.get() {
+            return;
+        }
+        let index = match self.current_art_index() {
+            Some(index) => index,
+            None => return,
+        };
+
+        self.update_metadata();
+        let before = imp.metadata.borrow().clone();
+        let mut art = match before.art() {
+            FieldValue::Set(art) => art.clone(),
+            _ => return,
+        };
+        let image = match art.get_mut(index) {
+            Some(image) => image,
+            None => return,
+        };
+
+        if let Some(pic_type) = PICTURE_TYPES.get(imp.art_type_dropdown.selected() as usize) {
+            image.set_pic_type(*pic_type);
+        }
+        let description = imp.art_description_text.text();
+        image.set_description((!description.is_empty()).then(|| description.to_string()));
+
+        imp.metadata.borrow_mut().set_art(FieldValue::Set(art));
+        let after = imp.metadata.borrow().clone();
+        if before != after {
+            imp.history.borrow_mut().record(Operation::Edit {
+                field,
+                coalesce,
+                before,
+                after,
+            });
+        }
+        self.notify_changed();
+    }
+
+    // Changes the art the tracks share, recording each change as its
+    // own undo step. `change` is given the index of the image in view,
+    // and returns the index of the image to show afterwards
+    fn edit_art(&self, change: impl FnOnce(&mut Vec<Art>, usize) -> usize) {
+        self.update_metadata();
+        let imp = self.imp();
+        let before = imp.metadata.borrow().clone();
+
+        // Tracks with differing art all get the new art
+        let mut art = match before.art() {
+            FieldValue::Set(art) => art.clone(),
+            _ => Vec::new(),
+        };
+        let index = change(&mut art, self.current_art_index().unwrap_or(0));
+
+        let mut after = before.clone();
+        after.set_art(match art.is_empty() {
+            true => FieldValue::Cleared,
+            false => FieldValue::Set(art),
+        });
+        if before == after {
+            return;
+        }
+
+        self.show_metadata(after.clone());
+        self.show_art_page(index);
+        imp.history.borrow_mut().record(Operation::Edit {
+            field: "art",
+            coalesce: false,
+            before,
+            after,
+        });
+        self.notify_changed();
+    }
+
+    /// Returns how images are reworked as they're added
+    pub fn art_options(&self) -> ArtOptions {
+        self.imp().art_options.get()
+    }
+
+    /// Sets how images are reworked as they're added
+    pub fn set_art_options(&self, options: ArtOptions) {
+        self.imp().art_options.set(options);
+    }
+
+    // Reworks the image as the art options ask.
+    // Images that can't be reworked are kept as they are
+    fn processed_art(&self, art: Art) -> Art {
+        match art.processed(&self.art_options()) {
+            Ok(processed) => processed,
+            Err(e) => {
+                log::warn!("unable to process image, {e}");
+                art
+            }
+        }
+    }
+
+    // Adds the image after the one in view, reworked as the art options ask
+    fn insert_art(&self, new_art: Art) {
+        let new_art = self.processed_art(new_art);
+        self.edit_art(move |art, index| {
+            let position = (index + 1).min(art.len());
+            art.insert(position, new_art);
+            position
+        });
+    }
+
+    fn show_art_save_chooser(&self) {
+        let art = match self.current_art() {
+            Some(art) => art,
+            None => return,
+        };
+        let window = self
+            .root()
+            .expect("failed to get editor page's root")
+            .downcast::<gtk::ApplicationWindow>()
+            .expect("failed to get editor page's application window");
+
+        let art_save_chooser = &self.imp().art_save_chooser;
+        art_save_chooser.set_transient_for(Some(&window));
+        art_save_chooser.set_current_name(&format!("cover.{}", art.extension()));
+        art_save_chooser.show();
+    }
+
+    // Offers the image as it's stored, and as a texture
+    // for applications that want another format
+    fn copy_art(&self) {
+        let art = match self.current_art() {
+            Some(art) => art,
+            None => return,
+        };
+
+        let bytes = glib::Bytes::from(art.data());
+        let mut providers = vec![gdk::ContentProvider::for_bytes(art.mime_type(), &bytes)];
+        let stream = gio::MemoryInputStream::from_bytes(&bytes);
+        if let Ok(pixbuf) = gtk::gdk_pixbuf::Pixbuf::from_stream(&stream, gio::Cancellable::NONE) {
+            let texture = gdk::Texture::for_pixbuf(&pixbuf);
+            providers.push(gdk::ContentProvider::for_value(&texture.to_value()));
+        }
+
+        let content = gdk::ContentProvider::new_union(&providers);
+        if let Err(e) = self.clipboard().set_content(Some(&content)) {
+            self.report_error(&format!("Couldn't copy the image, {e}"));
+        }
+    }
+
+    // Reads the image's data as the clipboard holds it, so
+    // a pasted JPEG isn't turned into a much larger PNG
+    fn paste_art(&self) {
+        let mime_types = [
+            "image/jpeg",
+            "image/png",
+            "image/gif",
+            "image/bmp",
+            "image/webp",
+        ];
+        self.clipboard().read_async(
+            &mime_types,
+            glib::PRIORITY_DEFAULT,
+            gio::Cancellable::NONE,
+            clone!(@weak self as page => move |result| {
+                let (stream, mime_type) = match result {
+                    Ok(read) => read,
+                    Err(e) => {
+                        page.report_error(&format!("Couldn't paste an image, {e}"));
+                        return;
+                    }
+                };
+
+                let output = gio::MemoryOutputStream::new_resizable();
+                output.splice_async(
+                    &stream,
+                    gio::OutputStreamSpliceFlags::CLOSE_SOURCE
+                        | gio::OutputStreamSpliceFlags::CLOSE_TARGET,
+                    glib::PRIORITY_DEFAULT,
+                    gio::Cancellable::NONE,
+                    clone!(@weak page, @strong output => move |result| {
+                        if let Err(e) = result {
+                            page.report_error(&format!("Couldn't paste the image, {e}"));
+                            return;
+                        }
+                        match Art::from_data(output.steal_as_bytes().to_vec()) {
+                            Some(art) => page.insert_art(art),
+                            None => page.report_error(&format!(
+                                "Couldn't paste the {mime_type}, it isn't an image that can be embedded"
+                            )),
+                        }
+                    }),
+                );
+            }),
+        );
+    }
+
+    fn show_art_page(&self, index: usize) {
+        let art_carousel = &self.imp().art_carousel;
+        if index < art_carousel.n_pages() as usize {
+            art_carousel.scroll_to(&art_carousel.nth_page(index as u32), false);
+        }
+    }
+
     fn clear_art_carousel(&self) {
         let imp = self.imp();
         let children = imp.art_carousel.observe_children();
@@ -176,7 +950,7 @@ impl MetanoteEditorPage {
         }
     }
 
-    fn set_textual_tags(&self, metadata: &MetadataContainer) {
+    fn set_textual_tags(&self, metadata: &MetadataEdit) {
         let imp = self.imp();
 
         let tags = [
@@ -213,140 +987,292 @@ impl MetanoteEditorPage {
         for tag in tags {
             self.set_text_value(tag.0, tag.1);
         }
+
+        for (field, entry) in imp.extra_entries.borrow().iter() {
+            let value = metadata
+                .extra()
+                .get(field)
+                .unwrap_or(&FieldValue::Unchanged);
+            self.set_text_value(entry, EntryValue::Text(value));
+        }
+
+        self.set_custom_items(metadata);
+    }
+
+    // Cleared items are left out, as they're about to be removed
+    fn set_custom_items(&self, metadata: &MetadataEdit) {
+        let imp = self.imp();
+        for (_, _, row) in imp.custom_entries.take() {
+            imp.custom_row.remove(&row);
+        }
+
+        let mut custom_entries = Vec::new();
+        for (key, value) in metadata.custom() {
+            if *value == FieldValue::Cleared {
+                continue;
+            }
+
+            let entry = Entry::builder().valign(Align::Center).build();
+            self.set_text_value(&entry, EntryValue::Text(value));
+            entry.connect_changed(clone!(@weak self as page => move |_| {
+                page.on_field_changed("custom");
+            }));
+
+            let remove_button = Button::builder()
+                .icon_name("list-remove-symbolic")
+                .tooltip_text("Remove Tag")
+                .valign(Align::Center)
+                .build();
+            remove_button.add_css_class("flat");
+            let removed_key = key.clone();
+            remove_button.connect_clicked(clone!(@weak self as page => move |_| {
+                page.remove_custom_item(&removed_key);
+            }));
+
+            let row = ActionRow::builder().title(key).build();
+            row.add_suffix(&entry);
+            row.add_suffix(&remove_button);
+            imp.custom_row.add_row(&row);
+            custom_entries.push((key.clone(), entry, row));
+        }
+        imp.custom_entries.replace(custom_entries);
+    }
+
+    fn add_custom_item(&self, key: String, value: String) {
+        self.set_custom_item(key, FieldValue::Set(value));
+    }
+
+    fn remove_custom_item(&self, key: &str) {
+        self.set_custom_item(key.to_string(), FieldValue::Cleared);
     }
 
-    fn set_text_value(&self, entry: &TemplateChild<Entry>, entry_value: EntryValue) {
+    fn set_custom_item(&self, key: String, value: FieldValue<String>) {
+        self.update_metadata();
+        let imp = self.imp();
+        let before = imp.metadata.borrow().clone();
+        imp.metadata.borrow_mut().custom_mut().insert(key, value);
+        let after = imp.metadata.borrow().clone();
+
+        self.show_metadata(after.clone());
+        imp.history.borrow_mut().record(Operation::Edit {
+            field: "custom",
+            coalesce: false,
+            before,
+            after,
+        });
+        self.notify_changed();
+    }
+
+    fn set_text_value(&self, entry: &Entry, entry_value: EntryValue) {
         entry.set_placeholder_text(None);
-        
+        entry.set_tooltip_text(None);
+
         match entry_value {
-            EntryValue::Text(v) => {
-                match v {
-                    Some(v) => {
-                        if v == "<Keep>" {
-                            entry.set_placeholder_text(Some(v));
-                            entry.set_text("");
-                        } else {
-                            entry.set_text(v);
-                        }
-                    }
-                    None => { entry.set_text("") },
-                }
-            }
-            EntryValue::Number(v) => {
-                match v {
-                    Some(v) => {
-                        let v = v.to_string();
-                        if v == "-1" {
-                            entry.set_text("");
-                            entry.set_placeholder_text(Some("<Keep>"));
-                        } else {
-                            entry.set_text(&v);
-                        }
-                    }
-                    None => { entry.set_text("") }
-                }
+            EntryValue::Text(v) => self.show_field_value(entry, v, |t| t.to_owned()),
+            EntryValue::Number(v) => self.show_field_value(entry, v, |n| n.to_string()),
+        }
+    }
+
+    // Mixed fields are left empty, with a placeholder counting the
+    // distinct values and a tooltip listing them
+    fn show_field_value<T>(
+        &self,
+        entry: &Entry,
+        value: &FieldValue<T>,
+        to_text: impl Fn(&T) -> String,
+    ) {
+        match value {
+            FieldValue::Set(v) => entry.set_text(&to_text(v)),
+            FieldValue::Mixed(values) => {
+                entry.set_text("");
+                entry.set_placeholder_text(Some(&format!("Keep {} values", values.len())));
+
+                let listed: Vec<String> = values
+                    .iter()
+                    .map(|v| v.as_ref().map(&to_text).unwrap_or_else(|| "(empty)".into()))
+                    .collect();
+                entry.set_tooltip_text(Some(&listed.join("\n")));
             }
+            FieldValue::Unchanged | FieldValue::Cleared => entry.set_text(""),
         }
     }
 
-    /// Writes metadata to whichever tracks editor has
-    pub fn write_metadata<T: MetadataWriteCapable>(&self, metadata_agent: &T) -> Result<()> {
-        self.update_metadata();
+    /// Writes the metadata each of the given rows holds,
+    /// reporting how each track fared. Call `commit` first
+    /// so edits still in the editor are included
+    pub fn write_metadata<T: MetadataWriteCapable>(
+        &self,
+        tracks: &[MetanoteRow],
+        metadata_agent: &T,
+    ) -> SaveReport {
         let imp = self.imp();
-        let tracks = imp.metanote_rows.borrow();
-        let current_metadata = imp.metadata.borrow();
 
+        let mut report = Vec::new();
+        let mut saved = Vec::new();
         for track in tracks.iter() {
-            track.replace_metadata(&current_metadata);
-            match track.write_metadata(metadata_agent) {
-                Ok(_) => (),
-                Err(e) => log::error!(
-                    "failed to write metadata for file at path {} - {}",
-                    track.imp().path.borrow().to_str().unwrap(),
-                    e
-                ),
+            let before = track.saved_metadata();
+            let result = track.write_metadata(metadata_agent);
+            match &result {
+                Ok(_) => saved.push(SavedTrack {
+                    row: track.clone(),
+                    before,
+                    after: track.imp().metadata.borrow().clone(),
+                }),
+                Err(e) => log::error!("failed to write metadata, {e}"),
             }
+            track.set_save_result(&result);
+            report.push((track.clone(), result));
         }
-        Ok(())
-    }
 
+        if !saved.is_empty() {
+            imp.history
+                .borrow_mut()
+                .record(Operation::Save { tracks: saved });
+        }
+        self.notify_changed();
+        report
+    }
 
-    // Replace instance's metadata with what has been modified in UI
+    // Replace instance's metadata with what has been modified in UI.
+    // Fields are compared with the merged baseline rather than the last
+    // edit, so emptying an entry typed into brings back its differing values
     fn update_metadata(&self) {
+        let imp = self.imp();
+        let original = imp.metadata.take();
+        let baseline = imp.baseline.borrow();
 
-        let updated_metadata = crate::metadata::MetadataContainerBuilder::default()
-            .title(StringOption::from(&self.imp().title_text).0)
-            .artist(StringOption::from(&self.imp().artist_text).0)
-            .album_artist(StringOption::from(&self.imp().album_artist_text).0)
-            .album(StringOption::from(&self.imp().album_text).0)
-            .track_number(I32Option::from(&self.imp().track_number_text).0)
-            .track_total(I32Option::from(&self.imp().track_total_text).0)
-            .genre(StringOption::from(&self.imp().genre_text).0)
-            .year(StringOption::from(&self.imp().year_text).0)
-            .disc_number(I32Option::from(&self.imp().disc_number_text).0)
-            .disc_total(I32Option::from(&self.imp().disc_total_text).0)
-            .composer(StringOption::from(&self.imp().composer_text).0)
-            .comment(StringOption::from(&self.imp().comment_text).0)
-            .copyright(StringOption::from(&self.imp().copyright_text).0)
-            .art(self.imp().metadata.take().art().to_owned())
+        let updated_metadata = metanote_core::metadata::MetadataEditBuilder::default()
+            .title(text_field(&imp.title_text, baseline.title()))
+            .artist(text_field(&imp.artist_text, baseline.artist()))
+            .album_artist(text_field(&imp.album_artist_text, baseline.album_artist()))
+            .album(text_field(&imp.album_text, baseline.album()))
+            .track_number(number_field(
+                &imp.track_number_text,
+                original.track_number(),
+                baseline.track_number(),
+            ))
+            .track_total(number_field(
+                &imp.track_total_text,
+                original.track_total(),
+                baseline.track_total(),
+            ))
+            .genre(text_field(&imp.genre_text, baseline.genre()))
+            .year(text_field(&imp.year_text, baseline.year()))
+            .disc_number(number_field(
+                &imp.disc_number_text,
+                original.disc_number(),
+                baseline.disc_number(),
+            ))
+            .disc_total(number_field(
+                &imp.disc_total_text,
+                original.disc_total(),
+                baseline.disc_total(),
+            ))
+            .composer(text_field(&imp.composer_text, baseline.composer()))
+            .comment(text_field(&imp.comment_text, baseline.comment()))
+            .copyright(text_field(&imp.copyright_text, baseline.copyright()))
+            .art(original.art().to_owned())
+            .extra(
+                imp.extra_entries
+                    .borrow()
+                    .iter()
+                    .map(|(field, entry)| {
+                        let baseline = baseline
+                            .extra()
+                            .get(field)
+                            .unwrap_or(&FieldValue::Unchanged);
+                        (*field, text_field(entry, baseline))
+                    })
+                    .collect(),
+            )
+            .custom(
+                original
+                    .custom()
+                    .iter()
+                    .map(|(key, original)| {
+                        let custom_entries = imp.custom_entries.borrow();
+                        let value = match custom_entries.iter().find(|(k, _, _)| k == key) {
+                            Some((_, entry, _)) => text_field(
+                                entry,
+                                baseline.custom().get(key).unwrap_or(&FieldValue::Unchanged),
+                            ),
+                            None => original.clone(),
+                        };
+                        (key.clone(), value)
+                    })
+                    .collect(),
+            )
             .build()
             .unwrap();
 
-        self.imp().metadata.replace(updated_metadata);
+        drop(baseline);
+        imp.metadata.replace(updated_metadata);
     }
 }
 
-// Struct to wrap an Option<String> so we can add traits to it
-struct StringOption(Option<String>);
-impl From<&TemplateChild<Entry>> for StringOption {
-    fn from(entry: &TemplateChild<Entry>) -> Self {
-        if entry.placeholder_text().is_some() && entry.text().len() == 0 {
-            StringOption(Some(entry.placeholder_text().unwrap().to_string()))
-        } else if entry.text().len() > 0 {
-            StringOption(Some(entry.text().to_string()))
-        } else {
-            StringOption(None)
+// Reads a text field back from its entry. An empty entry clears the
+// field unless the tracks held differing values for it
+fn text_field(entry: &Entry, baseline: &FieldValue<String>) -> FieldValue<String> {
+    let text = entry.text();
+    let input = (!text.is_empty()).then(|| text.to_string());
+    FieldValue::from_input(input, baseline)
+}
+
+// Reads a numeric field back from its entry. Text that isn't a number
+// leaves the field as the last edit had it
+fn number_field(
+    entry: &Entry,
+    original: &FieldValue<i32>,
+    baseline: &FieldValue<i32>,
+) -> FieldValue<i32> {
+    let text = entry.text();
+    if text.is_empty() {
+        return FieldValue::from_input(None, baseline);
+    }
+    match text.trim().parse::<i32>() {
+        Ok(n) => FieldValue::Set(n),
+        Err(e) => {
+            log::warn!("ignoring non-numeric value \"{text}\", {e}");
+            original.clone()
         }
     }
 }
 
-// Struct to wrap an Option<i32> so we can add traits to it
-struct I32Option(Option<i32>);
-impl From<&TemplateChild<Entry>> for I32Option {
-    fn from(entry: &TemplateChild<Entry>) -> Self {
-        if entry.placeholder_text().is_some() && entry.text().len() == 0 {
-            I32Option(Some(-1))
-        } else if entry.text().len() > 0 {
-            I32Option(Some(entry.text().parse::<i32>().expect("failed to parse i32")))
-        } else {
-            I32Option(None)
-        }
+fn set_action_enabled(group: &gio::SimpleActionGroup, name: &str, enabled: bool) {
+    if let Some(action) = group
+        .lookup_action(name)
+        .and_then(|action| action.downcast::<gio::SimpleAction>().ok())
+    {
+        action.set_enabled(enabled);
     }
 }
 
 impl ArtButtonChangeNotifiable for MetanoteEditorPage {
+    // Gathers the images the carousel's buttons hold, leaving
+    // out any empty button waiting for an image
     fn on_art_change(&self) {
         let art_carousel = &self.imp().art_carousel;
-        let mut artwork = Vec::new();
+        let artwork: Vec<Art> = (0..art_carousel.n_pages())
+            .filter_map(|i| art_carousel.nth_page(i).downcast::<ArtButton>().ok())
+            .filter_map(|button| button.imp().art.borrow().clone())
+            .collect();
 
-        for i in 0..art_carousel.n_pages() {
-            let art_button = art_carousel.nth_page(i).downcast::<ArtButton>().unwrap();
+        self.edit_art(move |art, index| {
+            *art = artwork;
+            index
+        });
+    }
 
-            match &art_button.imp().path.borrow().deref() {
-                Some(art_path) => {
-                    let art = Art::from_path(art_path).unwrap();
-                    artwork.push(art);
-                }
-                None => {
-                    artwork.pop();
-                }
-            };
-        }
-        self.imp().metadata.borrow_mut().set_art(Some(artwork));
+    fn process_art(&self, art: Art) -> Art {
+        self.processed_art(art)
+    }
+
+    fn on_art_error(&self, message: &str) {
+        self.report_error(message);
     }
 }
 
 enum EntryValue<'a> {
-    Text(&'a Option<String>),
-    Number(&'a Option<i32>),
+    Text(&'a FieldValue<String>),
+    Number(&'a FieldValue<i32>),
 }
@@ -0,0 +1,108 @@
+// history.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use metanote_core::metadata::{MetadataContainer, MetadataEdit};
+
+use crate::row::MetanoteRow;
+
+/// A reversible change made in the editor
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Operation {
+    /// A field in the editor changed. Consecutive keystrokes in the
+    /// same entry, marked by `coalesce`, are folded into one operation
+    Edit {
+        field: &'static str,
+        coalesce: bool,
+        before: MetadataEdit,
+        after: MetadataEdit,
+    },
+    /// Tracks were written to disk
+    Save { tracks: Vec<SavedTrack> },
+    /// Tracks were changed together outside the editor, such as by
+    /// find and replace, and are yet to be written
+    Batch { tracks: Vec<SavedTrack> },
+}
+
+/// A track's metadata on either side of a save or batch change
+#[derive(Clone, Debug)]
+pub struct SavedTrack {
+    pub row: MetanoteRow,
+    pub before: MetadataContainer,
+    pub after: MetadataContainer,
+}
+
+/// Undo and redo stacks of operations
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Operation>,
+    redo_stack: Vec<Operation>,
+}
+
+impl History {
+    /// Records a new operation, discarding anything that could be redone
+    pub fn record(&mut self, operation: Operation) {
+        self.redo_stack.clear();
+
+        if let (
+            Some(Operation::Edit {
+                field: last_field,
+                coalesce: true,
+                after: last_after,
+                ..
+            }),
+            Operation::Edit {
+                field,
+                coalesce: true,
+                after,
+                ..
+            },
+        ) = (self.undo_stack.last_mut(), &operation)
+        {
+            if last_field == field {
+                *last_after = after.clone();
+                return;
+            }
+        }
+
+        self.undo_stack.push(operation);
+    }
+
+    /// Returns the operation to reverse, moving it to the redo stack
+    pub fn undo(&mut self) -> Option<Operation> {
+        let operation = self.undo_stack.pop()?;
+        self.redo_stack.push(operation.clone());
+        Some(operation)
+    }
+
+    /// Returns the operation to reapply, moving it to the undo stack
+    pub fn redo(&mut self) -> Option<Operation> {
+        let operation = self.redo_stack.pop()?;
+        self.undo_stack.push(operation.clone());
+        Some(operation)
+    }
+
+    /// Drops editor field changes, which only apply to the
+    /// selection they were made on. Saves and batch changes are kept
+    pub fn forget_edits(&mut self) {
+        let kept = |o: &Operation| matches!(o, Operation::Save { .. } | Operation::Batch { .. });
+        self.undo_stack.retain(kept);
+        self.redo_stack.retain(kept);
+    }
+}
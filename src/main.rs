@@ -21,7 +21,8 @@ mod app;
 mod art_button;
 mod config;
 mod editor_page;
-mod metadata;
+mod history;
+mod picture;
 mod row;
 mod window;
 
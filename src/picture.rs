@@ -0,0 +1,80 @@
+// picture.rs
+//
+// Copyright 2022 Brian Reading <brian.reading@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use gtk::prelude::*;
+
+use gtk::gdk;
+
+use metanote_core::metadata::Art;
+
+/// A trait that gives artwork from
+/// the tag engine the ability to be
+/// shown as a GTK widget
+pub trait ToPictureWidget {
+    fn to_picture_widget(&self) -> gtk::Picture;
+    fn to_paintable(&self) -> Option<gdk::Paintable>;
+}
+
+impl ToPictureWidget for Art {
+    /// Returns a GTK Picture widget, showing a placeholder
+    /// if the image is damaged or can't be decoded
+    fn to_picture_widget(&self) -> gtk::Picture {
+        let paintable = match self.to_paintable() {
+            Some(paintable) => paintable,
+            None => return placeholder_picture(),
+        };
+
+        let picture = gtk::Picture::for_paintable(&paintable);
+        picture.set_alternative_text(self.description().as_ref().map(|d| d.as_str()));
+        picture
+    }
+
+    /// Decodes the image, returning `None` if it can't be
+    fn to_paintable(&self) -> Option<gdk::Paintable> {
+        let bytes = gtk::glib::Bytes::from(self.data());
+        let stream = gtk::gio::MemoryInputStream::from_bytes(&bytes);
+        match gtk::gdk_pixbuf::Pixbuf::from_stream(&stream, gtk::gio::Cancellable::NONE) {
+            Ok(pixbuf) => Some(gdk::Texture::for_pixbuf(&pixbuf).upcast()),
+            Err(e) => {
+                log::warn!("unable to decode {} image, {e}", self.mime_type());
+                None
+            }
+        }
+    }
+}
+
+fn placeholder_picture() -> gtk::Picture {
+    let picture = gtk::Picture::new();
+    if let Some(display) = gdk::Display::default() {
+        let icon = gtk::IconTheme::for_display(&display).lookup_icon(
+            "image-missing",
+            &[],
+            128,
+            1,
+            gtk::TextDirection::None,
+            gtk::IconLookupFlags::empty(),
+        );
+        picture.set_paintable(Some(&icon));
+    }
+    picture.set_alternative_text(Some("Damaged image"));
+    picture.set_tooltip_text(Some(
+        "This image is damaged or in a format that can't be shown",
+    ));
+    picture
+}
@@ -29,7 +29,13 @@ use gtk::glib::Object;
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
 
-use crate::metadata::{MetadataContainer, MetadataReadCapable, MetadataWriteCapable};
+use metanote_core::error::MetadataError;
+use metanote_core::metadata::{
+    Art, MetadataContainer, MetadataEdit, MetadataReadCapable, MetadataWriteCapable, PictureType,
+    TagType,
+};
+
+use crate::picture::ToPictureWidget;
 
 mod imp {
     use super::*;
@@ -39,7 +45,11 @@ mod imp {
         // MetadataContainer acts as the main record.
         // All views on this row simply reflect it.
         pub metadata: RefCell<MetadataContainer>,
+        // What was last read from or written to disk,
+        // so unsaved changes to the record can be found
+        pub saved_metadata: RefCell<MetadataContainer>,
         pub path: RefCell<PathBuf>,
+        pub modified_icon: RefCell<Option<gtk::Image>>,
     }
 
     #[glib::object_subclass]
@@ -67,6 +77,11 @@ gtk::glib::wrapper! {
 impl MetanoteRow {
     pub fn new<T: MetadataReadCapable>(path: &Path, metadata_agent: &T) -> Result<Self> {
         let metadata = metadata_agent.metadata(path)?;
+        Self::with_metadata(path, metadata)
+    }
+
+    /// Creates a row for metadata that has already been read from `path`
+    pub fn with_metadata(path: &Path, metadata: MetadataContainer) -> Result<Self> {
         let file_name = path
             .file_name()
             .context("{path} is a bad path")?
@@ -80,9 +95,13 @@ impl MetanoteRow {
             ("height-request", &80),
         ])?;
 
+        let cover = metadata
+            .art()
+            .as_ref()
+            .and_then(|art| Art::cover(art))
+            .and_then(|art| art.to_paintable());
         let avatar;
-        if let Some(art) = metadata.art() {
-            let cover = art[0].to_picture_widget().paintable().expect("bad art");
+        if let Some(cover) = cover {
             avatar = Avatar::new(50, None, false);
             avatar.set_custom_image(Some(&cover));
             row.add_prefix(&avatar);
@@ -91,9 +110,23 @@ impl MetanoteRow {
             row.add_prefix(&avatar);
         };
 
+        if !metadata.warnings().is_empty() {
+            let warnings: Vec<String> = metadata.warnings().iter().map(|w| w.to_string()).collect();
+            let warning_icon = gtk::Image::from_icon_name("dialog-warning-symbolic");
+            warning_icon.set_tooltip_text(Some(&warnings.join("\n")));
+            row.add_suffix(&warning_icon);
+        }
+
+        let modified_icon = gtk::Image::from_icon_name("document-edit-symbolic");
+        modified_icon.set_tooltip_text(Some("Unsaved changes"));
+        modified_icon.set_visible(false);
+        row.add_suffix(&modified_icon);
+
         let imp = row.imp();
         imp.path.replace(path.to_path_buf());
+        imp.saved_metadata.replace(metadata.clone());
         imp.metadata.replace(metadata);
+        imp.modified_icon.replace(Some(modified_icon));
 
         Ok(row)
     }
@@ -105,55 +138,132 @@ impl MetanoteRow {
         format!("{artist} - {title}")
     }
 
-    pub fn replace_metadata(&self, metadata: &MetadataContainer) {
-        let current = &self.imp().metadata;
-        let new = metadata;
-
-        let replacement_metadata = crate::metadata::MetadataContainerBuilder::default()
-            .title(self.replace_tag(current.borrow().title(), new.title()))
-            .artist(self.replace_tag(current.borrow().artist(), new.artist()))
-            .album_artist(self.replace_tag(current.borrow().album_artist(), new.album_artist()))
-            .album(self.replace_tag(current.borrow().album(), new.album()))
-            .track_number(self.replace_num_tag(current.borrow().track_number(), new.track_number()))
-            .track_total(self.replace_num_tag(current.borrow().track_total(), new.track_total()))
-            .genre(self.replace_tag(current.borrow().genre(), new.genre()))
-            .year(self.replace_tag(current.borrow().year(), new.year()))
-            .disc_number(self.replace_num_tag(current.borrow().disc_number(), new.disc_number()))
-            .disc_total(self.replace_num_tag(current.borrow().disc_total(), new.disc_total()))
-            .composer(self.replace_tag(current.borrow().composer(), new.composer()))
-            .comment(self.replace_tag(current.borrow().comment(), new.comment()))
-            .copyright(self.replace_tag(current.borrow().copyright(), new.copyright()))
-            .art(new.art().to_owned())
-            .build()
-            .unwrap();
-
-        self.imp().metadata.replace(replacement_metadata);
-    }
-
-    fn replace_tag(
+    /// Applies an edit to the metadata that the row holds.
+    /// Fields left `Unchanged` or `Mixed` keep the row's own value
+    pub fn replace_metadata(&self, edit: &MetadataEdit) {
+        let replacement_metadata = self.imp().metadata.borrow().apply(edit);
+        self.set_metadata(replacement_metadata);
+    }
+
+    /// Replaces the metadata that the row holds
+    pub fn set_metadata(&self, metadata: MetadataContainer) {
+        self.set_title(&Self::title_from_metadata(&metadata));
+        self.imp().metadata.replace(metadata);
+        self.update_modified_icon();
+    }
+
+    /// Points the row at the file after it's been moved
+    pub fn set_path(&self, path: &Path) {
+        if let Some(file_name) = path.file_name() {
+            self.set_subtitle(&file_name.to_string_lossy());
+        }
+        self.imp().path.replace(path.to_path_buf());
+    }
+
+    /// Returns the metadata as it was last read from or written to disk
+    pub fn saved_metadata(&self) -> MetadataContainer {
+        self.imp().saved_metadata.borrow().clone()
+    }
+
+    /// Returns true if the row holds changes not yet written to disk
+    pub fn is_modified(&self) -> bool {
+        let imp = self.imp();
+        *imp.metadata.borrow() != *imp.saved_metadata.borrow()
+    }
+
+    /// Throws away changes not yet written to disk
+    pub fn revert(&self) {
+        self.set_metadata(self.saved_metadata());
+    }
+
+    /// Reads the row's file again, showing the given tag, or the
+    /// primary one if there's none given. Unsaved changes are lost
+    pub fn reload<T: MetadataReadCapable>(
         &self,
-        current_tag: &Option<String>,
-        new_tag: &Option<String>,
-    ) -> Option<String> {
-        if new_tag == &Some(String::from("<Keep>")) {
-            current_tag.to_owned() // Don't try to write a "<Keep>" value
-        } else {
-            new_tag.to_owned()
+        metadata_agent: &T,
+        tag_type: Option<TagType>,
+    ) -> Result<(), MetadataError> {
+        let path = self.imp().path.borrow().clone();
+        let metadata = match tag_type {
+            Some(tag_type) => metadata_agent.tag_metadata(&path, tag_type)?,
+            None => metadata_agent.metadata(&path)?,
+        };
+
+        self.imp().saved_metadata.replace(metadata.clone());
+        self.set_metadata(metadata);
+        Ok(())
+    }
+
+    /// Has the next save write the row's fields to every tag in its file
+    pub fn sync_tags(&self) {
+        let mut metadata = self.imp().metadata.borrow().clone();
+        metadata.set_write_to(metadata.tag_types().clone());
+        self.set_metadata(metadata);
+    }
+
+    /// Has the next save also write the row's fields to a tag
+    /// the file doesn't hold yet, creating it
+    pub fn add_tag(&self, tag_type: TagType) {
+        let mut metadata = self.imp().metadata.borrow().clone();
+        if metadata.supported_tag_types().contains(&tag_type)
+            && !metadata.write_to().contains(&tag_type)
+        {
+            let mut write_to = metadata.write_to().clone();
+            write_to.push(tag_type);
+            metadata.set_write_to(write_to);
         }
+        self.set_metadata(metadata);
     }
 
-    fn replace_num_tag(&self, current_tag: &Option<i32>, new_tag: &Option<i32>) -> Option<i32> {
-        if new_tag == &Some(-1) {
-            current_tag.to_owned() // Don't try to write a -1 value
-        } else {
-            new_tag.to_owned()
+    /// Has the next save embed `cover` as the row's front cover,
+    /// in place of any front cover the row already has
+    pub fn embed_cover(&self, cover: Art) {
+        let mut metadata = self.imp().metadata.borrow().clone();
+        let mut art: Vec<Art> = metadata
+            .art()
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|art| *art.pic_type() != PictureType::CoverFront)
+            .collect();
+        art.insert(0, cover);
+        metadata.set_art(Some(art));
+        self.set_metadata(metadata);
+    }
+
+    fn update_modified_icon(&self) {
+        if let Some(icon) = self.imp().modified_icon.borrow().as_ref() {
+            icon.set_visible(self.is_modified());
         }
     }
 
     /// Writes to file whatever metadata that the row holds
-    pub fn write_metadata<T: MetadataWriteCapable>(&self, metadata_agent: &T) -> Result<()> {
+    pub fn write_metadata<T: MetadataWriteCapable>(
+        &self,
+        metadata_agent: &T,
+    ) -> Result<(), MetadataError> {
         let imp = self.imp();
-        metadata_agent.write_metadata(&imp.path.borrow(), &imp.metadata.borrow())
+        metadata_agent.write_metadata(&imp.path.borrow(), &imp.metadata.borrow())?;
+
+        let written = imp.metadata.borrow().after_write();
+        imp.metadata.replace(written.clone());
+        imp.saved_metadata.replace(written);
+        self.update_modified_icon();
+        Ok(())
+    }
+
+    /// Marks the row as failed if its last save went wrong
+    pub fn set_save_result(&self, result: &Result<(), MetadataError>) {
+        match result {
+            Ok(_) => {
+                self.remove_css_class("error");
+                self.set_tooltip_text(None);
+            }
+            Err(e) => {
+                self.add_css_class("error");
+                self.set_tooltip_text(Some(&format!("Not saved, {e}")));
+            }
+        }
     }
 }
 
@@ -161,7 +271,7 @@ impl MetanoteRow {
 mod tests {
 
     use super::*;
-    use crate::metadata::MetadataAgent;
+    use metanote_core::metadata::MetadataAgent;
 
     #[test]
     fn bad_path_throws_error() {
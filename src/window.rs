@@ -21,22 +21,50 @@ use adw::prelude::*;
 use adw::subclass::prelude::*;
 use gtk::subclass::prelude::*;
 
-use adw::{Leaflet, WindowTitle};
-use anyhow::Result;
+use adw::{ActionRow, Leaflet, Toast, ToastOverlay, WindowTitle};
 use gtk::gio;
-use gtk::gio::{File, FileInfo};
+use gtk::gio::{File, SimpleAction};
 use gtk::glib;
 use gtk::glib::subclass::InitializingObject;
-use gtk::glib::{clone, Object};
+use gtk::glib::{clone, Object, ToVariant};
+use gtk::{gdk, Align, CheckButton, DropTarget, FileFilter};
 use gtk::{
-    Button, CompositeTemplate, FileChooserAction, FileChooserNative, ListBox, ResponseType, Stack,
+    Button, ButtonsType, CompositeTemplate, FileChooserAction, FileChooserNative, ListBox,
+    MenuButton, MessageDialog, MessageType, ProgressBar, ResponseType, Revealer, Stack,
 };
 use gtk_macros::action;
+use metanote_core::artwork::ArtOptions;
+use metanote_core::cleanup::{
+    clean_text, parse_exceptions, CleanupOptionsBuilder, TextCase, DEFAULT_TITLE_CASE_EXCEPTIONS,
+};
+use metanote_core::error::MetadataError;
+use metanote_core::field::{ExtraField, Field};
+use metanote_core::filename::{edit_from_fields, parse_path, DEFAULT_FILENAME_PATTERN};
+use metanote_core::metadata::{
+    cover_exports, tag_type_name, Art, FieldValue, MetadataAgent, MetadataContainer, MetadataEdit,
+    MetadataReadCapable, MetadataWriteCapable, TagType, TAG_TYPES,
+};
+use metanote_core::numbering::{
+    auto_number, NumberingOptions, NumberingOptionsBuilder, NumberingOrder, NumberingRestart,
+    NumberingTrack,
+};
+use metanote_core::rename::{
+    default_rename_base, plan_renames, rename_file, Rename, DEFAULT_RENAME_TEMPLATE,
+};
+use metanote_core::replace::{edit_from_replacements, plan_changes, Replacement, Replacer};
+use metanote_core::scan::{
+    find_folder_art, visit_audio_files, ScanOptions, ScanOptionsBuilder, FOLDER_ART_PATTERNS,
+};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use crate::app::MetanoteApplication;
 use crate::config::PROFILE;
-use crate::editor_page::MetanoteEditorPage;
-use crate::metadata::MetadataAgent;
+use crate::editor_page::{MetanoteEditorPage, SaveReport};
+use crate::picture::ToPictureWidget;
 use crate::row::MetanoteRow;
 
 mod imp {
@@ -46,19 +74,44 @@ mod imp {
     #[template(resource = "/com/gitlab/bmreading/Metanote/window.ui")]
     pub struct MetanoteApplicationWindow {
         pub file_chooser: FileChooserNative,
+        pub files_chooser: FileChooserNative,
 
         #[template_child]
         pub leaflet: TemplateChild<Leaflet>,
         #[template_child]
+        pub toast_overlay: TemplateChild<ToastOverlay>,
+        #[template_child]
         pub content_stack: TemplateChild<Stack>,
         #[template_child]
         pub tracklist: TemplateChild<ListBox>,
         #[template_child]
+        pub scan_revealer: TemplateChild<Revealer>,
+        #[template_child]
+        pub scan_progress: TemplateChild<ProgressBar>,
+        #[template_child]
+        pub scan_cancel_button: TemplateChild<Button>,
+        #[template_child]
         pub main_title: TemplateChild<WindowTitle>,
         #[template_child]
         pub save_button: TemplateChild<Button>,
         #[template_child]
+        pub tags_button: TemplateChild<MenuButton>,
+        #[template_child]
         pub back_button: TemplateChild<Button>,
+
+        // Set to stop the folder scan in progress, if any
+        pub scan_cancelled: RefCell<Option<Arc<AtomicBool>>>,
+        pub scan_read: Cell<usize>,
+        pub scan_failures: RefCell<Vec<String>>,
+        pub scan_depth: Cell<Option<usize>>,
+        pub follow_symlinks: Cell<bool>,
+        // The file names looked for when embedding folder images
+        pub art_patterns: RefCell<Vec<String>>,
+        // The template last used to rename tracks
+        pub rename_template: RefCell<String>,
+        // The pattern last used to read tags from file names
+        pub filename_pattern: RefCell<String>,
+        pub title_case_exceptions: RefCell<String>,
     }
 
     #[glib::object_subclass]
@@ -76,14 +129,41 @@ mod imp {
                 .action(FileChooserAction::SelectFolder)
                 .build();
 
+            let audio_filter = FileFilter::new();
+            audio_filter.set_name(Some("Audio Files"));
+            audio_filter.add_mime_type("audio/*");
+            let files_chooser = FileChooserNative::builder()
+                .modal(true)
+                .action(FileChooserAction::Open)
+                .select_multiple(true)
+                .filter(&audio_filter)
+                .build();
+
             Self {
                 file_chooser,
+                files_chooser,
                 leaflet: TemplateChild::default(),
+                toast_overlay: TemplateChild::default(),
                 content_stack: TemplateChild::default(),
                 tracklist: TemplateChild::default(),
+                scan_revealer: TemplateChild::default(),
+                scan_progress: TemplateChild::default(),
+                scan_cancel_button: TemplateChild::default(),
                 main_title: TemplateChild::default(),
                 save_button: TemplateChild::default(),
+                tags_button: TemplateChild::default(),
                 back_button: TemplateChild::default(),
+                scan_cancelled: Default::default(),
+                scan_read: Default::default(),
+                scan_failures: Default::default(),
+                scan_depth: Default::default(),
+                follow_symlinks: Default::default(),
+                art_patterns: RefCell::new(
+                    FOLDER_ART_PATTERNS.iter().map(|p| p.to_string()).collect(),
+                ),
+                rename_template: RefCell::new(DEFAULT_RENAME_TEMPLATE.to_string()),
+                filename_pattern: RefCell::new(DEFAULT_FILENAME_PATTERN.to_string()),
+                title_case_exceptions: RefCell::new(DEFAULT_TITLE_CASE_EXCEPTIONS.to_string()),
             }
         }
 
@@ -101,6 +181,8 @@ mod imp {
             self.parent_constructed(obj);
             obj.bind_editor_page();
             obj.setup_actions();
+            obj.setup_scan_actions();
+            obj.setup_art_actions();
             obj.setup_callbacks();
 
             // Sets a devel style if a development was specified
@@ -111,7 +193,15 @@ mod imp {
     }
 
     impl WidgetImpl for MetanoteApplicationWindow {}
-    impl WindowImpl for MetanoteApplicationWindow {}
+    impl WindowImpl for MetanoteApplicationWindow {
+        fn close_request(&self, window: &Self::Type) -> gtk::Inhibit {
+            if window.has_unsaved_changes() {
+                window.confirm_discard(|window| window.destroy());
+                return gtk::Inhibit(true);
+            }
+            self.parent_close_request(window)
+        }
+    }
     impl ApplicationWindowImpl for MetanoteApplicationWindow {}
     impl AdwApplicationWindowImpl for MetanoteApplicationWindow {}
 }
@@ -144,24 +234,304 @@ impl MetanoteApplicationWindow {
             self,
             "open",
             clone!(@weak self as window => move |_, _| {
-                let file_chooser = &window.imp().file_chooser;
-                file_chooser.set_transient_for(Some(&window));
+                if window.has_unsaved_changes() {
+                    window.confirm_discard(|window| window.show_folder_chooser());
+                } else {
+                    window.show_folder_chooser();
+                }
+            })
+        );
 
-                file_chooser.connect_response(clone!(@weak window => move |fc, response| {
-                    if response == ResponseType::Accept {
-                        window.add_tracks(&fc.file().expect("Could not retrieve folder from file chooser"));
+        action!(
+            self,
+            "open-files",
+            clone!(@weak self as window => move |_, _| {
+                if window.has_unsaved_changes() {
+                    window.confirm_discard(|window| window.show_files_chooser());
+                } else {
+                    window.show_files_chooser();
+                }
+            })
+        );
+
+        action!(
+            self,
+            "view-tag",
+            Some(glib::VariantTy::STRING),
+            clone!(@weak self as window => move |_, parameter| {
+                if let Some(tag_type) = tag_type_from(parameter) {
+                    if window.has_unsaved_changes() {
+                        window.confirm_discard(move |window| window.view_tag(tag_type));
+                    } else {
+                        window.view_tag(tag_type);
                     }
-                    fc.destroy();
-                }));
+                }
+            })
+        );
+
+        action!(
+            self,
+            "sync-tags",
+            clone!(@weak self as window => move |_, _| {
+                window.sync_tags();
+            })
+        );
+
+        action!(
+            self,
+            "add-tag",
+            Some(glib::VariantTy::STRING),
+            clone!(@weak self as window => move |_, parameter| {
+                if let Some(tag_type) = tag_type_from(parameter) {
+                    window.add_tag(tag_type);
+                }
+            })
+        );
+
+        action!(
+            self,
+            "remove-tag",
+            Some(glib::VariantTy::STRING),
+            clone!(@weak self as window => move |_, parameter| {
+                if let Some(tag_type) = tag_type_from(parameter) {
+                    window.confirm_remove_tag(tag_type);
+                }
+            })
+        );
+
+        action!(
+            self,
+            "export-covers",
+            clone!(@weak self as window => move |_, _| {
+                window.export_covers();
+            })
+        );
+
+        action!(
+            self,
+            "embed-folder-art",
+            clone!(@weak self as window => move |_, _| {
+                window.show_folder_art_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "shrink-art",
+            clone!(@weak self as window => move |_, _| {
+                window.shrink_art();
+            })
+        );
+
+        action!(
+            self,
+            "rename-files",
+            clone!(@weak self as window => move |_, _| {
+                window.show_rename_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "tags-from-filenames",
+            clone!(@weak self as window => move |_, _| {
+                window.show_filename_pattern_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "find-replace",
+            clone!(@weak self as window => move |_, _| {
+                window.show_find_replace_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "clean-up-text",
+            clone!(@weak self as window => move |_, _| {
+                window.show_cleanup_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "auto-number",
+            clone!(@weak self as window => move |_, _| {
+                window.show_auto_number_dialog();
+            })
+        );
+
+        action!(
+            self,
+            "undo",
+            clone!(@weak self as window => move |_, _| {
+                let agent = MetadataAgent::new();
+                if let Some(report) = window.editor_page().undo(&agent) {
+                    window.show_save_report(&report);
+                }
+            })
+        );
 
-                file_chooser.show();
+        action!(
+            self,
+            "redo",
+            clone!(@weak self as window => move |_, _| {
+                let agent = MetadataAgent::new();
+                if let Some(report) = window.editor_page().redo(&agent) {
+                    window.show_save_report(&report);
+                }
             })
         );
     }
 
+    // Stateful actions backing the scan options in the primary menu
+    fn setup_scan_actions(&self) {
+        // A depth of -1 stands for no limit
+        let scan_depth = SimpleAction::new_stateful(
+            "scan-depth",
+            Some(glib::VariantTy::INT32),
+            &(-1i32).to_variant(),
+        );
+        scan_depth.connect_activate(clone!(@weak self as window => move |action, parameter| {
+            if let Some(depth) = parameter.and_then(|p| p.get::<i32>()) {
+                action.set_state(&depth.to_variant());
+                window.imp().scan_depth.set(usize::try_from(depth).ok());
+            }
+        }));
+        self.add_action(&scan_depth);
+
+        let follow_symlinks =
+            SimpleAction::new_stateful("follow-symlinks", None, &false.to_variant());
+        follow_symlinks.connect_activate(clone!(@weak self as window => move |action, _| {
+            let follow = !window.imp().follow_symlinks.get();
+            action.set_state(&follow.to_variant());
+            window.imp().follow_symlinks.set(follow);
+        }));
+        self.add_action(&follow_symlinks);
+    }
+
+    // Stateful actions backing the embedded image options in the primary
+    // menu. A size or quality of 0 leaves images as they are
+    fn setup_art_actions(&self) {
+        let art_size = SimpleAction::new_stateful(
+            "art-size",
+            Some(glib::VariantTy::INT32),
+            &0i32.to_variant(),
+        );
+        art_size.connect_activate(clone!(@weak self as window => move |action, parameter| {
+            if let Some(size) = parameter.and_then(|p| p.get::<i32>()) {
+                action.set_state(&size.to_variant());
+                window.update_art_options(|options| {
+                    options.set_max_size(u32::try_from(size).ok().filter(|size| *size > 0));
+                });
+            }
+        }));
+        self.add_action(&art_size);
+
+        let art_quality = SimpleAction::new_stateful(
+            "art-quality",
+            Some(glib::VariantTy::INT32),
+            &0i32.to_variant(),
+        );
+        art_quality.connect_activate(clone!(@weak self as window => move |action, parameter| {
+            if let Some(quality) = parameter.and_then(|p| p.get::<i32>()) {
+                action.set_state(&quality.to_variant());
+                window.update_art_options(|options| {
+                    options.set_jpeg_quality(u8::try_from(quality).ok().filter(|q| *q > 0));
+                });
+            }
+        }));
+        self.add_action(&art_quality);
+
+        let convert_png = SimpleAction::new_stateful("convert-png", None, &false.to_variant());
+        convert_png.connect_activate(clone!(@weak self as window => move |action, _| {
+            let convert = !window.editor_page().art_options().convert_png();
+            action.set_state(&convert.to_variant());
+            window.update_art_options(|options| {
+                options.set_convert_png(convert);
+            });
+        }));
+        self.add_action(&convert_png);
+
+        let strip_exif = SimpleAction::new_stateful("strip-exif", None, &false.to_variant());
+        strip_exif.connect_activate(clone!(@weak self as window => move |action, _| {
+            let strip = !window.editor_page().art_options().strip_exif();
+            action.set_state(&strip.to_variant());
+            window.update_art_options(|options| {
+                options.set_strip_exif(strip);
+            });
+        }));
+        self.add_action(&strip_exif);
+    }
+
+    fn update_art_options<F: FnOnce(&mut ArtOptions)>(&self, change: F) {
+        let editor_page = self.editor_page();
+        let mut options = editor_page.art_options();
+        change(&mut options);
+        editor_page.set_art_options(options);
+    }
+
+    fn show_folder_chooser(&self) {
+        let file_chooser = &self.imp().file_chooser;
+        file_chooser.set_transient_for(Some(self));
+        file_chooser.show();
+    }
+
+    fn show_files_chooser(&self) {
+        let files_chooser = &self.imp().files_chooser;
+        files_chooser.set_transient_for(Some(self));
+        files_chooser.show();
+    }
+
+    fn editor_page(&self) -> MetanoteEditorPage {
+        self.imp()
+            .content_stack
+            .child_by_name("editor_page")
+            .expect("editor page was never added")
+            .downcast::<MetanoteEditorPage>()
+            .expect("editor page has the wrong type")
+    }
+
     fn setup_callbacks(&self) {
         let imp = self.imp();
 
+        imp.file_chooser
+            .connect_response(clone!(@weak self as window => move |fc, response| {
+                if response == ResponseType::Accept {
+                    window.open_files(&[fc.file().expect("Could not retrieve folder from file chooser")]);
+                }
+                fc.destroy();
+            }));
+
+        imp.files_chooser
+            .connect_response(clone!(@weak self as window => move |fc, response| {
+                if response == ResponseType::Accept {
+                    let model = fc.files();
+                    let files: Vec<File> = (0..model.n_items())
+                        .filter_map(|i| model.item(i)?.downcast::<File>().ok())
+                        .collect();
+                    window.open_files(&files);
+                }
+                fc.destroy();
+            }));
+
+        // Files and folders dropped from a file manager
+        let drop_target = DropTarget::new(gdk::FileList::static_type(), gdk::DragAction::COPY);
+        drop_target.connect_drop(
+            clone!(@weak self as window => @default-return false, move |_, value, _, _| {
+                match value.get::<gdk::FileList>() {
+                    Ok(file_list) => {
+                        window.confirm_open(file_list.files());
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }),
+        );
+        self.add_controller(&drop_target);
+
         imp.tracklist
             .connect_selected_rows_changed(clone!(@weak self as window => move |tracklist| {
                 let content_stack = &window.imp().content_stack;
@@ -181,17 +551,18 @@ impl MetanoteApplicationWindow {
                 } else {
                     content_stack.set_visible_child_name("status_page");
                 }
+                window.update_tags_menu();
         }));
 
         imp.save_button
             .connect_clicked(clone!(@weak self as window => move |_| {
-                let editor_page = window.imp().content_stack.child_by_name("editor_page").unwrap().downcast::<MetanoteEditorPage>().unwrap();
-                let agent = MetadataAgent::new();
-                match editor_page.write_metadata(&agent) {
-                    Ok(_) => (),
-                    Err(e) => log::error!("Failed to save tracks, {}", e),
-                };
-        }));
+                window.save();
+            }));
+
+        imp.scan_cancel_button
+            .connect_clicked(clone!(@weak self as window => move |_| {
+                window.cancel_scan();
+            }));
 
         imp.back_button
             .connect_clicked(clone!(@weak self as window => move |_| {
@@ -199,49 +570,1430 @@ impl MetanoteApplicationWindow {
             }));
     }
 
-    fn add_tracks(&self, dir: &File) {
-        let tracklist = &self.imp().tracklist;
-        self.clear_tracklist();
+    /// Writes every row with unsaved changes, returning true if all were saved
+    fn save(&self) -> bool {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+
+        let modified: Vec<MetanoteRow> = self
+            .rows()
+            .into_iter()
+            .filter(|r| r.is_modified())
+            .collect();
+        let agent = MetadataAgent::new();
+        let report = editor_page.write_metadata(&modified, &agent);
+        self.show_save_report(&report);
+        self.update_tags_menu();
+
+        report.iter().all(|(_, result)| result.is_ok())
+    }
+
+    fn has_unsaved_changes(&self) -> bool {
+        self.editor_page().has_pending_edits() || self.rows().iter().any(|r| r.is_modified())
+    }
 
-        let tracks = self.parse_dir(dir).expect("couldn't parse tracks");
+    fn update_save_button(&self) {
+        let has_unsaved_changes = self.has_unsaved_changes();
+        self.imp().save_button.set_sensitive(has_unsaved_changes);
+    }
 
-        for track in tracks {
-            let path = dir.path().unwrap().join(track.name().as_path());
+    // Asks whether to save or discard unsaved changes,
+    // then calls `then` unless the user cancelled
+    fn confirm_discard<F: Fn(&Self) + 'static>(&self, then: F) {
+        let dialog = MessageDialog::builder()
+            .transient_for(self)
+            .modal(true)
+            .message_type(MessageType::Question)
+            .text("Save changes before continuing?")
+            .secondary_text("Changes that aren't saved will be lost.")
+            .build();
+        dialog.add_buttons(&[
+            ("Cancel", ResponseType::Cancel),
+            ("Discard", ResponseType::Reject),
+            ("Save", ResponseType::Accept),
+        ]);
+        if let Some(discard_button) = dialog.widget_for_response(ResponseType::Reject) {
+            discard_button.add_css_class("destructive-action");
+        }
 
-            let agent = MetadataAgent::new();
-            let row = crate::row::MetanoteRow::new(&path, &agent);
-            match row {
-                Ok(row) => tracklist.append(&row),
-                Err(err) => log::warn!("unable to display track, {err}"),
+        dialog.connect_response(clone!(@weak self as window => move |dialog, response| {
+            dialog.destroy();
+            match response {
+                ResponseType::Accept if window.save() => then(&window),
+                ResponseType::Reject => {
+                    window.discard_changes();
+                    then(&window);
+                }
+                _ => (),
             }
+        }));
+
+        dialog.show();
+    }
+
+    fn discard_changes(&self) {
+        for row in self.rows() {
+            row.revert();
         }
+        self.editor_page().discard_edits();
     }
 
-    fn clear_tracklist(&self) {
-        let tracklist = &self.imp().tracklist;
-        tracklist.select_all();
+    fn selected_rows(&self) -> Vec<MetanoteRow> {
+        self.imp()
+            .tracklist
+            .selected_rows()
+            .into_iter()
+            .filter_map(|row| row.downcast::<MetanoteRow>().ok())
+            .collect()
+    }
 
-        for track in tracklist.selected_rows() {
-            tracklist.remove(&track);
+    // Offers each kind of tag found in the selected files
+    fn update_tags_menu(&self) {
+        let rows = self.selected_rows();
+        let mut tag_types: Vec<TagType> = Vec::new();
+        let mut missing_tag_types: Vec<TagType> = Vec::new();
+        for row in &rows {
+            let metadata = row.imp().metadata.borrow();
+            for tag_type in metadata.tag_types() {
+                if !tag_types.contains(tag_type) {
+                    tag_types.push(*tag_type);
+                }
+            }
+            for tag_type in metadata.supported_tag_types() {
+                if !metadata.tag_types().contains(tag_type) && !missing_tag_types.contains(tag_type)
+                {
+                    missing_tag_types.push(*tag_type);
+                }
+            }
+        }
+
+        let view_section = gio::Menu::new();
+        let remove_section = gio::Menu::new();
+        for tag_type in tag_types {
+            let name = tag_type_name(tag_type);
+
+            let view_item = gio::MenuItem::new(Some(&format!("Show {name}")), None);
+            view_item.set_action_and_target_value(Some("win.view-tag"), Some(&name.to_variant()));
+            view_section.append_item(&view_item);
+
+            let remove_item = gio::MenuItem::new(Some(&format!("Remove {name}")), None);
+            remove_item
+                .set_action_and_target_value(Some("win.remove-tag"), Some(&name.to_variant()));
+            remove_section.append_item(&remove_item);
+        }
+
+        let sync_section = gio::Menu::new();
+        sync_section.append(Some("Write to All Tags"), Some("win.sync-tags"));
+        for tag_type in missing_tag_types {
+            let name = tag_type_name(tag_type);
+            let add_item = gio::MenuItem::new(Some(&format!("Also Write {name}")), None);
+            add_item.set_action_and_target_value(Some("win.add-tag"), Some(&name.to_variant()));
+            sync_section.append_item(&add_item);
+        }
+
+        let art_section = gio::Menu::new();
+        art_section.append(
+            Some("Embed Images from Album Folders…"),
+            Some("win.embed-folder-art"),
+        );
+        art_section.append(
+            Some("Export Covers to Album Folders"),
+            Some("win.export-covers"),
+        );
+        art_section.append(Some("Shrink Embedded Images"), Some("win.shrink-art"));
+
+        let edit_section = gio::Menu::new();
+        edit_section.append(Some("Find and Replace…"), Some("win.find-replace"));
+        edit_section.append(Some("Clean Up Text…"), Some("win.clean-up-text"));
+        edit_section.append(Some("Number Tracks…"), Some("win.auto-number"));
+
+        let files_section = gio::Menu::new();
+        files_section.append(Some("Rename from Tags…"), Some("win.rename-files"));
+        files_section.append(
+            Some("Tags from File Names…"),
+            Some("win.tags-from-filenames"),
+        );
+
+        let menu = gio::Menu::new();
+        menu.append_section(None, &view_section);
+        menu.append_section(None, &sync_section);
+        menu.append_section(None, &remove_section);
+        menu.append_section(None, &edit_section);
+        menu.append_section(None, &art_section);
+        menu.append_section(None, &files_section);
+
+        let tags_button = &self.imp().tags_button;
+        tags_button.set_menu_model(Some(&menu));
+        tags_button.set_visible(!rows.is_empty());
+    }
+
+    // Shows the given tag of each selected file that has one
+    fn view_tag(&self, tag_type: TagType) {
+        let agent = MetadataAgent::new();
+        let rows = self.selected_rows();
+        for row in &rows {
+            if !row.imp().metadata.borrow().tag_types().contains(&tag_type) {
+                continue;
+            }
+            if let Err(e) = row.reload(&agent, Some(tag_type)) {
+                log::warn!("unable to read {} tag, {e}", tag_type_name(tag_type));
+            }
+        }
+
+        // The rows were read again, so there's nothing left to keep
+        self.editor_page().discard_edits();
+        self.update_save_button();
+    }
+
+    fn sync_tags(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        for row in self.selected_rows() {
+            row.sync_tags();
+        }
+        editor_page.discard_edits();
+        self.update_save_button();
+
+        let toast = Toast::new("Saving will write these fields to every tag");
+        self.imp().toast_overlay.add_toast(&toast);
+    }
+
+    // Files that can't hold the tag are left as they are
+    fn add_tag(&self, tag_type: TagType) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        for row in self.selected_rows() {
+            row.add_tag(tag_type);
         }
+        editor_page.discard_edits();
+        self.update_save_button();
+
+        let toast = Toast::new(&format!(
+            "Saving will add a {} tag",
+            tag_type_name(tag_type)
+        ));
+        self.imp().toast_overlay.add_toast(&toast);
     }
 
-    fn parse_dir(&self, dir: &File) -> Result<Vec<FileInfo>> {
-        let file_enumerator = dir.enumerate_children(
-            "*",
-            gio::FileQueryInfoFlags::NOFOLLOW_SYMLINKS,
-            Some(&gio::Cancellable::new()),
-        )?;
+    fn confirm_remove_tag(&self, tag_type: TagType) {
+        let rows: Vec<MetanoteRow> = self
+            .selected_rows()
+            .into_iter()
+            .filter(|row| row.imp().metadata.borrow().tag_types().contains(&tag_type))
+            .collect();
+        let name = tag_type_name(tag_type);
+
+        let dialog = MessageDialog::builder()
+            .transient_for(self)
+            .modal(true)
+            .message_type(MessageType::Question)
+            .text(&format!("Remove the {name} tag from {} tracks?", rows.len()))
+            .secondary_text("The tag is deleted from the files right away, along with any unsaved changes to those tracks.")
+            .build();
+        dialog.add_buttons(&[
+            ("Cancel", ResponseType::Cancel),
+            ("Remove", ResponseType::Accept),
+        ]);
+        if let Some(remove_button) = dialog.widget_for_response(ResponseType::Accept) {
+            remove_button.add_css_class("destructive-action");
+        }
+
+        dialog.connect_response(clone!(@weak self as window => move |dialog, response| {
+            dialog.destroy();
+            if response == ResponseType::Accept {
+                window.remove_tag(&rows, tag_type);
+            }
+        }));
+
+        dialog.show();
+    }
+
+    fn remove_tag(&self, rows: &[MetanoteRow], tag_type: TagType) {
+        let agent = MetadataAgent::new();
+        let report: SaveReport = rows
+            .iter()
+            .map(|row| {
+                let path = row.imp().path.borrow().clone();
+                let result = agent
+                    .remove_tag(&path, tag_type)
+                    .and_then(|_| row.reload(&agent, None));
+                row.set_save_result(&result);
+                (row.clone(), result)
+            })
+            .collect();
+
+        self.editor_page().discard_edits();
+        self.update_tags_menu();
+        self.update_save_button();
+        self.show_report(&report, "Removed the tag from", "changed");
+    }
+
+    // Writes a cover image into each folder among the selected
+    // tracks, leaving any cover file already there alone
+    fn export_covers(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+        self.update_save_button();
+
+        let tracks: Vec<(PathBuf, Vec<Art>)> = self
+            .selected_rows()
+            .iter()
+            .map(|row| {
+                let art = row.imp().metadata.borrow().art().clone();
+                (row.imp().path.borrow().clone(), art.unwrap_or_default())
+            })
+            .collect();
+        let exports = cover_exports(
+            tracks
+                .iter()
+                .map(|(path, art)| (path.as_path(), art.as_slice())),
+        );
 
-        let mut audio_tracks = Vec::new();
-        for child in file_enumerator {
-            let file_info = child?;
-            if file_info.content_type().unwrap().contains("audio") {
-                audio_tracks.push(file_info);
+        let mut exported = 0;
+        let mut existing = 0;
+        let mut failures = Vec::new();
+        for (path, art) in &exports {
+            if path.exists() {
+                existing += 1;
+                continue;
+            }
+            match art.save_to_path(path) {
+                Ok(()) => exported += 1,
+                Err(e) => failures.push(e.to_string()),
             }
         }
 
-        Ok(audio_tracks)
+        if !failures.is_empty() {
+            self.show_failures(
+                &format!(
+                    "{} of {} covers couldn't be exported",
+                    failures.len(),
+                    exports.len()
+                ),
+                &failures,
+            );
+            return;
+        }
+
+        let message = match (exported, existing) {
+            (0, 0) => "None of the tracks have a cover".to_string(),
+            (1, 0) => "Exported 1 cover".to_string(),
+            (n, 0) => format!("Exported {n} covers"),
+            (n, 1) => format!("Exported {n} covers, 1 folder already had one"),
+            (n, m) => format!("Exported {n} covers, {m} folders already had one"),
+        };
+        self.imp().toast_overlay.add_toast(&Toast::new(&message));
+    }
+
+    // Looks for images such as cover.jpg beside the selected tracks,
+    // showing what was found in each folder before embedding it
+    fn show_folder_art_dialog(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+
+        let mut folders: Vec<(PathBuf, Vec<MetanoteRow>)> = Vec::new();
+        for row in self.selected_rows() {
+            let folder = match row.imp().path.borrow().parent() {
+                Some(folder) => folder.to_path_buf(),
+                None => continue,
+            };
+            match folders.iter_mut().find(|(f, _)| *f == folder) {
+                Some((_, rows)) => rows.push(row),
+                None => folders.push((folder, vec![row])),
+            }
+        }
+        let folders = Rc::new(folders);
+
+        let patterns_entry = gtk::Entry::builder()
+            .text(&self.imp().art_patterns.borrow().join(", "))
+            .placeholder_text("File names to look for, such as cover.*")
+            .tooltip_text("Tried in order, ignoring case. * stands for any run of characters")
+            .build();
+        let (dialog, folder_list) = self.batch_dialog(
+            "Embed Folder Images",
+            "Embed",
+            &[patterns_entry.upcast_ref()],
+        );
+        dialog.set_default_size(480, 480);
+
+        let found = Rc::new(RefCell::new(
+            self.fill_folder_art_list(&folder_list, &folders),
+        ));
+
+        patterns_entry.connect_activate(
+            clone!(@weak self as window, @weak folder_list, @strong found, @strong folders => move |entry| {
+                let patterns = entry
+                    .text()
+                    .split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                window.imp().art_patterns.replace(patterns);
+                found.replace(window.fill_folder_art_list(&folder_list, &folders));
+            }),
+        );
+
+        dialog.connect_response(
+            clone!(@weak self as window, @strong found => move |dialog, response| {
+                dialog.destroy();
+                if response != ResponseType::Accept {
+                    return;
+                }
+
+                let found = found.borrow();
+                let rows: Vec<MetanoteRow> = found
+                    .iter()
+                    .filter(|(_, _, include)| include.is_active())
+                    .flat_map(|(rows, _, _)| rows.iter().cloned())
+                    .collect();
+                window.editor_page().change_rows(&rows, || {
+                    for (rows, art, include) in found.iter() {
+                        if include.is_active() {
+                            for row in rows {
+                                row.embed_cover(art.clone());
+                            }
+                        }
+                    }
+                });
+                let embedded = rows.len();
+
+                window.finish_batch(&match embedded {
+                    1 => "Saving will embed the cover in 1 track".to_string(),
+                    n => format!("Saving will embed covers in {n} tracks"),
+                });
+            }),
+        );
+
+        dialog.show();
+    }
+
+    // Lists the image found in each folder, returning each one found
+    // along with its tracks and the check button that includes it
+    fn fill_folder_art_list(
+        &self,
+        folder_list: &ListBox,
+        folders: &[(PathBuf, Vec<MetanoteRow>)],
+    ) -> Vec<(Vec<MetanoteRow>, Art, CheckButton)> {
+        clear_list(folder_list);
+
+        let patterns = self.imp().art_patterns.borrow().clone();
+        let options = self.editor_page().art_options();
+        let mut found = Vec::new();
+        for (folder, rows) in folders {
+            let folder_name = folder
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| folder.display().to_string());
+            let folder_row = ActionRow::builder().title(&folder_name).build();
+
+            let image = find_folder_art(folder, &patterns)
+                .map_err(|e| MetadataError::from_io(folder, e))
+                .and_then(|path| match path {
+                    Some(path) => Art::from_path(&path).map(|art| Some((path, art))),
+                    None => Ok(None),
+                });
+            match image {
+                Ok(Some((path, art))) => {
+                    let picture = art.to_picture_widget();
+                    picture.set_size_request(64, 64);
+                    folder_row.add_prefix(&picture);
+
+                    let original_size = art.data().len();
+                    let art = art.processed(&options).unwrap_or_else(|e| {
+                        log::warn!("unable to process image, {e}");
+                        art
+                    });
+                    let size = match art.data().len() {
+                        size if size == original_size => glib::format_size(size as u64).to_string(),
+                        size => format!(
+                            "{} → {}",
+                            glib::format_size(original_size as u64),
+                            glib::format_size(size as u64)
+                        ),
+                    };
+
+                    let image_name = path.file_name().unwrap_or_default().to_string_lossy();
+                    folder_row.set_subtitle(&match rows.len() {
+                        1 => format!("{image_name}, {size}, for 1 track"),
+                        n => format!("{image_name}, {size}, for {n} tracks"),
+                    });
+
+                    let include = CheckButton::builder()
+                        .active(true)
+                        .valign(Align::Center)
+                        .build();
+                    folder_row.add_suffix(&include);
+                    folder_row.set_activatable_widget(Some(&include));
+                    found.push((rows.clone(), art, include));
+                }
+                Ok(None) => folder_row.set_subtitle("No image found"),
+                Err(e) => folder_row.set_subtitle(&e.to_string()),
+            }
+            folder_list.append(&folder_row);
+        }
+
+        found
+    }
+
+    // Reworks the selected tracks' images as the embedded
+    // image options ask, saying how much smaller they get
+    fn shrink_art(&self) {
+        let editor_page = self.editor_page();
+        let options = editor_page.art_options();
+        if options.is_empty() {
+            let toast = Toast::new("Choose how to shrink images from the main menu first");
+            self.imp().toast_overlay.add_toast(&toast);
+            return;
+        }
+
+        editor_page.commit();
+        let rows = self.selected_rows();
+        let mut before = 0;
+        let mut after = 0;
+        editor_page.change_rows(&rows, || {
+            for row in &rows {
+                let mut metadata = row.imp().metadata.borrow().clone();
+                let art = match metadata.art() {
+                    Some(art) => art.clone(),
+                    None => continue,
+                };
+
+                let shrunk: Vec<Art> = art
+                    .iter()
+                    .map(|image| {
+                        image.processed(&options).unwrap_or_else(|e| {
+                            log::warn!("unable to process image, {e}");
+                            image.clone()
+                        })
+                    })
+                    .collect();
+                before += art.iter().map(|image| image.data().len()).sum::<usize>();
+                after += shrunk.iter().map(|image| image.data().len()).sum::<usize>();
+
+                if shrunk != art {
+                    metadata.set_art(Some(shrunk));
+                    row.set_metadata(metadata);
+                }
+            }
+        });
+        editor_page.discard_edits();
+        self.update_save_button();
+
+        let message = if after < before {
+            format!(
+                "Saving will shrink the images from {} to {}",
+                glib::format_size(before as u64),
+                glib::format_size(after as u64)
+            )
+        } else {
+            "The images are already as small as the options make them".to_string()
+        };
+        self.imp().toast_overlay.add_toast(&Toast::new(&message));
+    }
+
+    // Moves the selected tracks to paths filled in from their tags,
+    // previewing each old and new path before anything is moved
+    fn show_rename_dialog(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+
+        let rows = Rc::new(self.selected_rows());
+        let paths: Vec<PathBuf> = rows
+            .iter()
+            .map(|row| row.imp().path.borrow().clone())
+            .collect();
+        let template = self.imp().rename_template.borrow().clone();
+
+        let template_entry = gtk::Entry::builder()
+            .text(&template)
+            .tooltip_text(
+                "Fields such as {title}, {album_artist} or {track:02}, with / between folders",
+            )
+            .build();
+        let base_entry = gtk::Entry::builder()
+            .text(&default_rename_base(&paths, &template).to_string_lossy())
+            .tooltip_text("The folder the template's paths start from")
+            .build();
+        let create_dirs_check = CheckButton::builder()
+            .label("Create missing folders")
+            .active(true)
+            .build();
+        let (dialog, rename_list) = self.batch_dialog(
+            "Rename from Tags",
+            "Rename",
+            &[
+                template_entry.upcast_ref(),
+                base_entry.upcast_ref(),
+                create_dirs_check.upcast_ref(),
+            ],
+        );
+
+        let renames = Rc::new(RefCell::new(Vec::new()));
+        let update_preview = Rc::new(
+            clone!(@weak self as window, @weak dialog, @weak template_entry,
+            @weak base_entry, @weak rename_list, @strong rows, @strong renames => move || {
+                let planned = window.fill_rename_list(
+                    &rename_list,
+                    &rows,
+                    &template_entry.text(),
+                    Path::new(base_entry.text().as_str()),
+                );
+                let can_rename = planned.iter().all(|rename| rename.to().is_ok());
+                dialog.set_response_sensitive(ResponseType::Accept, can_rename);
+                renames.replace(planned);
+            }),
+        );
+        update_preview();
+        template_entry.connect_changed(clone!(@strong update_preview => move |_| update_preview()));
+        base_entry.connect_changed(move |_| update_preview());
+
+        dialog.connect_response(
+            clone!(@weak self as window, @weak template_entry, @weak create_dirs_check,
+                @strong rows, @strong renames => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    window.imp().rename_template.replace(template_entry.text().to_string());
+                    window.rename_rows(&rows, &renames.borrow(), create_dirs_check.is_active());
+                }
+                dialog.destroy();
+            }),
+        );
+
+        dialog.show();
+    }
+
+    // Lists each track's old and new path, returning the renames planned
+    fn fill_rename_list(
+        &self,
+        rename_list: &ListBox,
+        rows: &[MetanoteRow],
+        template: &str,
+        base: &Path,
+    ) -> Vec<Rename> {
+        clear_list(rename_list);
+
+        let tracks: Vec<(PathBuf, MetadataContainer)> = rows
+            .iter()
+            .map(|row| {
+                let imp = row.imp();
+                (imp.path.borrow().clone(), imp.metadata.borrow().clone())
+            })
+            .collect();
+        let renames = plan_renames(&tracks, template, base);
+
+        for rename in &renames {
+            let rename_row = ActionRow::builder()
+                .subtitle(&rename.from().to_string_lossy())
+                .build();
+            match rename.to() {
+                Ok(to) => {
+                    let shown = to.strip_prefix(base).unwrap_or(to);
+                    rename_row.set_title(&shown.to_string_lossy());
+                }
+                Err(e) => {
+                    rename_row.set_title(&e.to_string());
+                    rename_row.add_css_class("error");
+                }
+            }
+            rename_list.append(&rename_row);
+        }
+
+        renames
+    }
+
+    fn rename_rows(&self, rows: &[MetanoteRow], renames: &[Rename], create_dirs: bool) {
+        let report: SaveReport = rows
+            .iter()
+            .zip(renames)
+            .filter_map(|(row, rename)| {
+                let to = rename.to().as_ref().ok()?;
+                if to == rename.from() {
+                    return None;
+                }
+
+                let result = rename_file(rename.from(), to, create_dirs);
+                if result.is_ok() {
+                    row.set_path(to);
+                }
+                Some((row.clone(), result))
+            })
+            .collect();
+
+        if report.is_empty() {
+            let toast = Toast::new("The tracks already have those names");
+            self.imp().toast_overlay.add_toast(&toast);
+            return;
+        }
+        self.show_report(&report, "Renamed", "renamed");
+    }
+
+    // Fills the selected tracks' fields from their file names
+    // with a pattern, previewing what's read from each first
+    fn show_filename_pattern_dialog(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+
+        let rows = Rc::new(self.selected_rows());
+
+        let pattern_entry = gtk::Entry::builder()
+            .text(&self.imp().filename_pattern.borrow())
+            .tooltip_text("Fields such as {track}, {artist} or {title}, {_} to skip text, and / to match folders too")
+            .build();
+        let (dialog, preview_list) = self.batch_dialog(
+            "Tags from File Names",
+            "Fill Tags",
+            &[pattern_entry.upcast_ref()],
+        );
+
+        let edits = Rc::new(RefCell::new(Vec::new()));
+        let update_preview = clone!(@weak self as window, @weak dialog, @weak preview_list,
+        @strong rows, @strong edits => move |entry: &gtk::Entry| {
+            let parsed = window.fill_filename_preview(&preview_list, &rows, &entry.text());
+            let can_fill = parsed.iter().any(|edit| edit.is_some());
+            dialog.set_response_sensitive(ResponseType::Accept, can_fill);
+            edits.replace(parsed);
+        });
+        update_preview(&pattern_entry);
+        pattern_entry.connect_changed(update_preview);
+
+        dialog.connect_response(
+            clone!(@weak self as window, @weak pattern_entry, @strong rows, @strong edits => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    window.imp().filename_pattern.replace(pattern_entry.text().to_string());
+                    window.fill_tags_from_filenames(&rows, &edits.borrow());
+                }
+                dialog.destroy();
+            }),
+        );
+
+        dialog.show();
+    }
+
+    // Lists the fields read from each track's path, returning the
+    // edit for each track, or `None` where nothing could be read
+    fn fill_filename_preview(
+        &self,
+        preview_list: &ListBox,
+        rows: &[MetanoteRow],
+        pattern: &str,
+    ) -> Vec<Option<MetadataEdit>> {
+        clear_list(preview_list);
+
+        rows.iter()
+            .map(|row| {
+                let path = row.imp().path.borrow().clone();
+                let preview_row = ActionRow::builder()
+                    .title(&path.file_name().unwrap_or_default().to_string_lossy())
+                    .build();
+
+                let parsed = parse_path(pattern, &path).and_then(|fields| {
+                    let edit = edit_from_fields(&fields)?;
+                    Ok((fields, edit))
+                });
+                let edit = match parsed {
+                    Ok((fields, edit)) => {
+                        let shown: Vec<String> = fields
+                            .iter()
+                            .map(|(name, value)| format!("{name}: {}", value.trim()))
+                            .collect();
+                        preview_row.set_subtitle(&shown.join(" · "));
+                        Some(edit)
+                    }
+                    Err(e) => {
+                        preview_row.set_subtitle(&e);
+                        preview_row.add_css_class("error");
+                        None
+                    }
+                };
+
+                preview_list.append(&preview_row);
+                edit
+            })
+            .collect()
+    }
+
+    fn fill_tags_from_filenames(&self, rows: &[MetanoteRow], edits: &[Option<MetadataEdit>]) {
+        let mut filled = 0;
+        self.editor_page().change_rows(rows, || {
+            for (row, edit) in rows.iter().zip(edits) {
+                if let Some(edit) = edit {
+                    row.replace_metadata(edit);
+                    filled += 1;
+                }
+            }
+        });
+
+        let skipped = rows.len() - filled;
+        let message = match (filled, skipped) {
+            (1, 0) => "Filled the tags of 1 track".to_string(),
+            (n, 0) => format!("Filled the tags of {n} tracks"),
+            (n, 1) => format!("Filled the tags of {n} tracks, 1 didn't match"),
+            (n, m) => format!("Filled the tags of {n} tracks, {m} didn't match"),
+        };
+        self.finish_batch(&message);
+    }
+
+    // A dialog for changing the selected tracks, with a button to apply
+    // the change and room for the controls choosing what it does
+    fn apply_dialog(&self, title: &str, apply_label: &str) -> gtk::Dialog {
+        let dialog = gtk::Dialog::builder()
+            .transient_for(self)
+            .modal(true)
+            .title(title)
+            .build();
+        dialog.add_buttons(&[
+            ("Cancel", ResponseType::Cancel),
+            (apply_label, ResponseType::Accept),
+        ]);
+        if let Some(apply_button) = dialog.widget_for_response(ResponseType::Accept) {
+            apply_button.add_css_class("suggested-action");
+        }
+
+        let content_area = dialog.content_area();
+        content_area.set_spacing(12);
+        content_area.set_margin_top(12);
+        content_area.set_margin_bottom(12);
+        content_area.set_margin_start(12);
+        content_area.set_margin_end(12);
+        dialog
+    }
+
+    // An apply dialog showing the given controls above a list
+    // previewing what happens to each track
+    fn batch_dialog(
+        &self,
+        title: &str,
+        apply_label: &str,
+        controls: &[&gtk::Widget],
+    ) -> (gtk::Dialog, ListBox) {
+        let dialog = self.apply_dialog(title, apply_label);
+        dialog.set_default_size(640, 560);
+
+        let preview_list = ListBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .build();
+        preview_list.add_css_class("boxed-list");
+        let scrolled_window = gtk::ScrolledWindow::builder()
+            .child(&preview_list)
+            .vexpand(true)
+            .build();
+
+        let content_area = dialog.content_area();
+        for control in controls {
+            content_area.append(*control);
+        }
+        content_area.append(&scrolled_window);
+        (dialog, preview_list)
+    }
+
+    // Refreshes the editor and save button after
+    // a batch change, saying what was done
+    fn finish_batch(&self, message: &str) {
+        self.editor_page().discard_edits();
+        self.update_save_button();
+        self.imp().toast_overlay.add_toast(&Toast::new(message));
+    }
+
+    fn show_find_replace_dialog(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+
+        let rows = Rc::new(self.selected_rows());
+        if rows.is_empty() {
+            return;
+        }
+
+        let find_entry = gtk::Entry::builder().placeholder_text("Find").build();
+        let replace_entry = gtk::Entry::builder()
+            .placeholder_text("Replace With")
+            .tooltip_text("With a regular expression, $1 or ${name} puts back what a group matched")
+            .build();
+        let ignore_case_check = CheckButton::with_label("Ignore Case");
+        let regex_check = CheckButton::with_label("Regular Expression");
+        let options_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        options_box.append(&ignore_case_check);
+        options_box.append(&regex_check);
+
+        let (field_box, field_checks) = text_field_checks();
+
+        let (dialog, preview_list) = self.batch_dialog(
+            "Find and Replace",
+            "Replace All",
+            &[
+                find_entry.upcast_ref(),
+                replace_entry.upcast_ref(),
+                options_box.upcast_ref(),
+                field_box.upcast_ref(),
+            ],
+        );
+
+        let plans = Rc::new(RefCell::new(Vec::new()));
+        let field_checks = Rc::new(field_checks);
+        let update_preview = Rc::new(
+            clone!(@weak self as window, @weak dialog, @weak preview_list, @weak find_entry,
+            @weak replace_entry, @weak ignore_case_check, @weak regex_check,
+            @strong rows, @strong plans, @strong field_checks => move || {
+                let fields = checked_fields(&field_checks);
+                let replacer = Replacer::new(
+                    &find_entry.text(),
+                    &replace_entry.text(),
+                    regex_check.is_active(),
+                    ignore_case_check.is_active(),
+                );
+
+                let planned = match &replacer {
+                    Ok(replacer) => {
+                        find_entry.remove_css_class("error");
+                        find_entry.set_tooltip_text(None);
+                        window.fill_replace_preview(&preview_list, &rows, |metadata| {
+                            replacer.plan(metadata, &fields)
+                        })
+                    }
+                    Err(e) => {
+                        if !find_entry.text().is_empty() {
+                            find_entry.add_css_class("error");
+                        }
+                        find_entry.set_tooltip_text(Some(e.as_str()));
+                        window.fill_replace_preview(&preview_list, &[], |_| Vec::new())
+                    }
+                };
+                let can_replace = planned.iter().any(|replacements| !replacements.is_empty());
+                dialog.set_response_sensitive(ResponseType::Accept, can_replace);
+                plans.replace(planned);
+            }),
+        );
+        update_preview();
+        for entry in [&find_entry, &replace_entry] {
+            entry.connect_changed(clone!(@strong update_preview => move |_| update_preview()));
+        }
+        for check in [&ignore_case_check, &regex_check]
+            .into_iter()
+            .chain(field_checks.iter().map(|(check, _)| check))
+        {
+            check.connect_toggled(clone!(@strong update_preview => move |_| update_preview()));
+        }
+
+        dialog.connect_response(
+            clone!(@weak self as window, @strong rows, @strong plans => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    window.apply_replacements(&rows, &plans.borrow(), "Replaced text in");
+                }
+                dialog.destroy();
+            }),
+        );
+
+        dialog.show();
+    }
+
+    // Lists what changes in each track, returning each track's
+    // replacements, empty for tracks left as they are
+    fn fill_replace_preview<F>(
+        &self,
+        preview_list: &ListBox,
+        rows: &[MetanoteRow],
+        plan: F,
+    ) -> Vec<Vec<Replacement>>
+    where
+        F: Fn(&MetadataContainer) -> Vec<Replacement>,
+    {
+        clear_list(preview_list);
+
+        rows.iter()
+            .map(|row| {
+                let imp = row.imp();
+                let replacements = plan(&imp.metadata.borrow());
+                if !replacements.is_empty() {
+                    let changes: Vec<String> = replacements
+                        .iter()
+                        .map(|replacement| {
+                            format!(
+                                "{}: {} → {}",
+                                replacement.field().label(),
+                                replacement.before(),
+                                replacement.after()
+                            )
+                        })
+                        .collect();
+                    let preview_row = ActionRow::builder()
+                        .title(
+                            &imp.path
+                                .borrow()
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy(),
+                        )
+                        .subtitle(&changes.join("\n"))
+                        .subtitle_lines(changes.len() as i32)
+                        .build();
+                    preview_list.append(&preview_row);
+                }
+                replacements
+            })
+            .collect()
+    }
+
+    // Stages each track's replacements, saying how many tracks changed
+    fn apply_replacements(&self, rows: &[MetanoteRow], plans: &[Vec<Replacement>], done: &str) {
+        let mut changed = 0;
+        self.editor_page().change_rows(rows, || {
+            for (row, replacements) in rows.iter().zip(plans) {
+                if !replacements.is_empty() {
+                    row.replace_metadata(&edit_from_replacements(replacements));
+                    changed += 1;
+                }
+            }
+        });
+
+        self.finish_batch(&match changed {
+            1 => format!("{done} 1 track"),
+            n => format!("{done} {n} tracks"),
+        });
+    }
+
+    // Converts case and tidies up text in the chosen fields
+    fn show_cleanup_dialog(&self) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+        editor_page.discard_edits();
+
+        let rows = Rc::new(self.selected_rows());
+        if rows.is_empty() {
+            return;
+        }
+
+        let case_dropdown = gtk::DropDown::from_strings(&[
+            "Keep Case",
+            "Title Case",
+            "Sentence case",
+            "UPPER CASE",
+            "lower case",
+        ]);
+        let exceptions_entry = gtk::Entry::builder()
+            .text(&self.imp().title_case_exceptions.borrow())
+            .hexpand(true)
+            .tooltip_text(
+                "Words title case leaves in lower case, unless they start or end the text",
+            )
+            .build();
+        let capitals_check = CheckButton::builder()
+            .label("Keep Capitals")
+            .tooltip_text("Leaves words written in capitals, such as ABBA, as they are")
+            .active(true)
+            .build();
+        let case_box = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        case_box.append(&case_dropdown);
+        case_box.append(&exceptions_entry);
+        case_box.append(&capitals_check);
+
+        let trim_check = CheckButton::builder()
+            .label("Trim Spaces")
+            .active(true)
+            .build();
+        let collapse_check = CheckButton::builder()
+            .label("Collapse Repeated Spaces")
+            .active(true)
+            .build();
+        let normalize_check = CheckButton::builder()
+            .label("Normalize Unicode")
+            .tooltip_text("Stores accented letters the same way everywhere, as NFC")
+            .active(true)
+            .build();
+        let control_check = CheckButton::builder()
+            .label("Remove Control Characters")
+            .active(true)
+            .build();
+        let options_box = gtk::FlowBox::builder()
+            .selection_mode(gtk::SelectionMode::None)
+            .max_children_per_line(4)
+            .build();
+        for check in [
+            &trim_check,
+            &collapse_check,
+            &normalize_check,
+            &control_check,
+        ] {
+            options_box.insert(check, -1);
+        }
+
+        let (field_box, field_checks) = text_field_checks();
+
+        let (dialog, preview_list) = self.batch_dialog(
+            "Clean Up Text",
+            "Clean Up",
+            &[
+                case_box.upcast_ref(),
+                options_box.upcast_ref(),
+                field_box.upcast_ref(),
+            ],
+        );
+
+        let plans = Rc::new(RefCell::new(Vec::new()));
+        let field_checks = Rc::new(field_checks);
+        let update_preview = Rc::new(
+            clone!(@weak self as window, @weak dialog, @weak preview_list, @weak case_dropdown,
+            @weak exceptions_entry, @weak capitals_check, @weak trim_check, @weak collapse_check, @weak normalize_check,
+            @weak control_check, @strong rows, @strong plans, @strong field_checks => move || {
+                let case = match case_dropdown.selected() {
+                    1 => Some(TextCase::Title),
+                    2 => Some(TextCase::Sentence),
+                    3 => Some(TextCase::Upper),
+                    4 => Some(TextCase::Lower),
+                    _ => None,
+                };
+                exceptions_entry.set_sensitive(case == Some(TextCase::Title));
+                capitals_check.set_sensitive(
+                    matches!(case, Some(TextCase::Title) | Some(TextCase::Sentence)),
+                );
+
+                let options = CleanupOptionsBuilder::default()
+                    .case(case)
+                    .title_case_exceptions(parse_exceptions(&exceptions_entry.text()))
+                    .keep_capitals(capitals_check.is_active())
+                    .trim_whitespace(trim_check.is_active())
+                    .collapse_whitespace(collapse_check.is_active())
+                    .normalize(normalize_check.is_active())
+                    .remove_control_characters(control_check.is_active())
+                    .build()
+                    .expect("failed to build cleanup options");
+                let fields = checked_fields(&field_checks);
+
+                let planned = window.fill_replace_preview(&preview_list, &rows, |metadata| {
+                    plan_changes(metadata, &fields, |text| Some(clean_text(text, &options)))
+                });
+                let can_clean = planned.iter().any(|replacements| !replacements.is_empty());
+                dialog.set_response_sensitive(ResponseType::Accept, can_clean);
+                plans.replace(planned);
+            }),
+        );
+        update_preview();
+        case_dropdown
+            .connect_selected_notify(clone!(@strong update_preview => move |_| update_preview()));
+        exceptions_entry
+            .connect_changed(clone!(@strong update_preview => move |_| update_preview()));
+        for check in [
+            &capitals_check,
+            &trim_check,
+            &collapse_check,
+            &normalize_check,
+            &control_check,
+        ]
+        .into_iter()
+        .chain(field_checks.iter().map(|(check, _)| check))
+        {
+            check.connect_toggled(clone!(@strong update_preview => move |_| update_preview()));
+        }
+
+        dialog.connect_response(
+            clone!(@weak self as window, @weak exceptions_entry, @strong rows, @strong plans => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    window.imp().title_case_exceptions.replace(exceptions_entry.text().to_string());
+                    window.apply_replacements(&rows, &plans.borrow(), "Cleaned up the text of");
+                }
+                dialog.destroy();
+            }),
+        );
+
+        dialog.show();
+    }
+
+    // Numbers the selected tracks in one go, in tracklist
+    // or file name order, optionally starting over per disc or folder
+    fn show_auto_number_dialog(&self) {
+        let dialog = self.apply_dialog("Number Tracks", "Number");
+
+        let start_spin = gtk::SpinButton::with_range(0.0, 9999.0, 1.0);
+        start_spin.set_value(1.0);
+        let step_spin = gtk::SpinButton::with_range(1.0, 100.0, 1.0);
+        let order_dropdown = gtk::DropDown::from_strings(&["Tracklist", "File Name"]);
+        let restart_dropdown = gtk::DropDown::from_strings(&["Never", "Each Disc", "Each Folder"]);
+        let fill_total_check = CheckButton::builder()
+            .label("Set track totals")
+            .active(true)
+            .build();
+
+        let grid = gtk::Grid::builder()
+            .row_spacing(12)
+            .column_spacing(12)
+            .build();
+        let labelled = [
+            ("Start At", start_spin.upcast_ref::<gtk::Widget>()),
+            ("Count By", step_spin.upcast_ref()),
+            ("Order", order_dropdown.upcast_ref()),
+            ("Start Over", restart_dropdown.upcast_ref()),
+        ];
+        for (row, (label, widget)) in labelled.into_iter().enumerate() {
+            let label = gtk::Label::builder()
+                .label(label)
+                .halign(Align::Start)
+                .build();
+            grid.attach(&label, 0, row as i32, 1, 1);
+            grid.attach(widget, 1, row as i32, 1, 1);
+        }
+        grid.attach(&fill_total_check, 0, labelled.len() as i32, 2, 1);
+        dialog.content_area().append(&grid);
+
+        dialog.connect_response(
+            clone!(@weak self as window, @weak start_spin, @weak step_spin, @weak order_dropdown,
+                @weak restart_dropdown, @weak fill_total_check => move |dialog, response| {
+                if response == ResponseType::Accept {
+                    let order = match order_dropdown.selected() {
+                        1 => NumberingOrder::FileName,
+                        _ => NumberingOrder::Given,
+                    };
+                    let restart = match restart_dropdown.selected() {
+                        1 => NumberingRestart::EachDisc,
+                        2 => NumberingRestart::EachFolder,
+                        _ => NumberingRestart::Never,
+                    };
+                    let options = NumberingOptionsBuilder::default()
+                        .start(start_spin.value_as_int())
+                        .step(step_spin.value_as_int())
+                        .order(order)
+                        .restart(restart)
+                        .fill_total(fill_total_check.is_active())
+                        .build()
+                        .expect("failed to build numbering options");
+                    window.auto_number(&options);
+                }
+                dialog.destroy();
+            }),
+        );
+
+        dialog.show();
+    }
+
+    fn auto_number(&self, options: &NumberingOptions) {
+        let editor_page = self.editor_page();
+        editor_page.commit();
+
+        let mut rows = self.selected_rows();
+        rows.sort_by_key(|row| row.index());
+        let tracks: Vec<NumberingTrack> = rows
+            .iter()
+            .map(|row| {
+                let imp = row.imp();
+                (
+                    imp.path.borrow().clone(),
+                    *imp.metadata.borrow().disc_number(),
+                )
+            })
+            .collect();
+
+        editor_page.change_rows(&rows, || {
+            for (row, (number, total)) in rows.iter().zip(auto_number(&tracks, options)) {
+                let mut edit = MetadataEdit::default();
+                edit.set_track_number(FieldValue::Set(number));
+                if let Some(total) = total {
+                    edit.set_track_total(FieldValue::Set(total));
+                }
+                row.replace_metadata(&edit);
+            }
+        });
+
+        self.finish_batch(&match rows.len() {
+            1 => "Numbered 1 track".to_string(),
+            n => format!("Numbered {n} tracks"),
+        });
+    }
+
+    fn rows(&self) -> Vec<MetanoteRow> {
+        let mut rows = Vec::new();
+        let mut child = self.imp().tracklist.first_child();
+        while let Some(widget) = child {
+            child = widget.next_sibling();
+            if let Ok(row) = widget.downcast::<MetanoteRow>() {
+                rows.push(row);
+            }
+        }
+        rows
+    }
+
+    fn show_save_report(&self, report: &SaveReport) {
+        self.show_report(report, "Saved", "saved");
+    }
+
+    // Success gets a toast, such as "Saved 3 tracks", while
+    // failures get a dialog listing each file and the reason
+    fn show_report(&self, report: &SaveReport, done: &str, failed: &str) {
+        let failures: Vec<String> = report
+            .iter()
+            .filter_map(|(row, result)| {
+                let e = result.as_ref().err()?;
+                Some(match e.path() {
+                    Some(_) => e.to_string(),
+                    None => format!("{}: {e}", row.imp().path.borrow().display()),
+                })
+            })
+            .collect();
+
+        if failures.is_empty() {
+            let toast = Toast::new(&match report.len() {
+                1 => format!("{done} 1 track"),
+                n => format!("{done} {n} tracks"),
+            });
+            self.imp().toast_overlay.add_toast(&toast);
+            return;
+        }
+
+        self.show_failures(
+            &format!(
+                "{} of {} tracks couldn't be {failed}",
+                failures.len(),
+                report.len()
+            ),
+            &failures,
+        );
+    }
+
+    fn show_failures(&self, text: &str, failures: &[String]) {
+        let dialog = MessageDialog::builder()
+            .transient_for(self)
+            .modal(true)
+            .message_type(MessageType::Error)
+            .buttons(ButtonsType::Close)
+            .text(text)
+            .secondary_text(&failures.join("\n"))
+            .build();
+        dialog.connect_response(|dialog, _| dialog.destroy());
+        dialog.show();
+    }
+
+    /// Opens the files once any unsaved changes are saved or discarded
+    pub fn confirm_open(&self, files: Vec<File>) {
+        if self.has_unsaved_changes() {
+            self.confirm_discard(move |window| window.open_files(&files));
+        } else {
+            self.open_files(&files);
+        }
+    }
+
+    // Replaces the tracklist with the given files, and
+    // the tracks found in any folders among them
+    fn open_files(&self, files: &[File]) {
+        self.cancel_scan();
+        self.clear_tracklist();
+
+        let paths: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file| {
+                let path = file.path();
+                if path.is_none() {
+                    log::warn!("only local files can be opened, skipping {}", file.uri());
+                }
+                path
+            })
+            .collect();
+
+        let imp = self.imp();
+        let options = ScanOptionsBuilder::default()
+            .max_depth(imp.scan_depth.get())
+            .follow_symlinks(imp.follow_symlinks.get())
+            .build()
+            .expect("failed to build scan options");
+        let cancelled = Arc::new(AtomicBool::new(false));
+        imp.scan_cancelled.replace(Some(cancelled.clone()));
+        imp.scan_failures.borrow_mut().clear();
+        imp.scan_read.set(0);
+
+        imp.scan_progress.set_fraction(0.0);
+        imp.scan_progress.set_text(Some("Looking for tracks…"));
+        imp.scan_revealer.set_reveal_child(true);
+
+        // Reading tags is slow for large libraries, so it happens on
+        // another thread, with rows sent back as they're read
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let worker_cancelled = cancelled.clone();
+        std::thread::spawn(move || scan_tracks(&paths, &options, &worker_cancelled, &sender));
+
+        receiver.attach(
+            None,
+            clone!(@weak self as window => @default-return glib::Continue(false), move |message| {
+                if cancelled.load(Ordering::Relaxed) {
+                    return glib::Continue(false);
+                }
+                window.on_scan_message(message)
+            }),
+        );
+    }
+
+    fn on_scan_message(&self, message: ScanMessage) -> glib::Continue {
+        let imp = self.imp();
+        match message {
+            ScanMessage::Track(path, metadata) => {
+                // How many tracks there are isn't known until the scan
+                // ends, so the bar pulses with each one read
+                let read = imp.scan_read.get() + 1;
+                imp.scan_read.set(read);
+                imp.scan_progress.pulse();
+                imp.scan_progress.set_text(Some(&match read {
+                    1 => "Read 1 track…".to_string(),
+                    n => format!("Read {n} tracks…"),
+                }));
+
+                let failure = match metadata {
+                    Ok(metadata) => match MetanoteRow::with_metadata(&path, metadata) {
+                        Ok(row) => {
+                            imp.tracklist.append(&row);
+                            None
+                        }
+                        Err(e) => Some(format!("{}: {e}", path.display())),
+                    },
+                    Err(e) => Some(match e.path() {
+                        Some(_) => e.to_string(),
+                        None => format!("{}: {e}", path.display()),
+                    }),
+                };
+                if let Some(failure) = failure {
+                    log::warn!("unable to display track, {failure}");
+                    imp.scan_failures.borrow_mut().push(failure);
+                }
+                glib::Continue(true)
+            }
+            ScanMessage::Failed(path, e) => {
+                imp.scan_failures
+                    .borrow_mut()
+                    .push(format!("{}: {e}", path.display()));
+                glib::Continue(true)
+            }
+            ScanMessage::Finished => {
+                self.finish_scan();
+
+                let failures = imp.scan_failures.take();
+                match failures.len() {
+                    0 => {}
+                    1 => self.show_failures("1 file couldn't be opened", &failures),
+                    n => self.show_failures(&format!("{n} files couldn't be opened"), &failures),
+                }
+                glib::Continue(false)
+            }
+        }
+    }
+
+    fn finish_scan(&self) {
+        let imp = self.imp();
+        imp.scan_cancelled.replace(None);
+        imp.scan_revealer.set_reveal_child(false);
+        self.update_save_button();
+    }
+
+    fn cancel_scan(&self) {
+        if let Some(cancelled) = self.imp().scan_cancelled.take() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+        self.finish_scan();
+    }
+
+    fn clear_tracklist(&self) {
+        let tracklist = &self.imp().tracklist;
+        tracklist.select_all();
+
+        for track in tracklist.selected_rows() {
+            tracklist.remove(&track);
+        }
     }
 
     fn bind_editor_page(&self) {
@@ -253,6 +2005,96 @@ impl MetanoteApplicationWindow {
         content_stack.add_named(&status_page, Some("status_page"));
 
         let editor_page = MetanoteEditorPage::new();
+        editor_page.connect_changed(clone!(@weak self as window => move |_| {
+            window.update_save_button();
+        }));
+        editor_page.connect_error(clone!(@weak self as window => move |_, message| {
+            window.imp().toast_overlay.add_toast(&Toast::new(message));
+        }));
         content_stack.add_named(&editor_page, Some("editor_page"));
     }
 }
+
+// Reads a tag type from an action's parameter, as named by `tag_type_name`
+fn tag_type_from(parameter: Option<&glib::Variant>) -> Option<TagType> {
+    let name = parameter?.str()?;
+    TAG_TYPES.into_iter().find(|t| tag_type_name(*t) == name)
+}
+
+// Removes every row from the list
+fn clear_list(list: &ListBox) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+}
+
+// Check buttons for picking the text fields a batch change works on,
+// each with the fields it stands for. The fields titles are most
+// often fixed in are picked to begin with
+fn text_field_checks() -> (gtk::FlowBox, Vec<(CheckButton, Vec<Field>)>) {
+    let field_box = gtk::FlowBox::builder()
+        .selection_mode(gtk::SelectionMode::None)
+        .max_children_per_line(5)
+        .build();
+    let mut field_checks: Vec<(CheckButton, Vec<Field>)> = Field::TEXT
+        .into_iter()
+        .map(|field| {
+            let check = CheckButton::builder()
+                .label(field.label())
+                .active(matches!(
+                    field,
+                    Field::Title | Field::Artist | Field::AlbumArtist | Field::Album
+                ))
+                .build();
+            (check, vec![field])
+        })
+        .collect();
+    field_checks.push((
+        CheckButton::with_label("Other Fields"),
+        ExtraField::ALL.into_iter().map(Field::Extra).collect(),
+    ));
+    for (check, _) in &field_checks {
+        field_box.insert(check, -1);
+    }
+    (field_box, field_checks)
+}
+
+fn checked_fields(field_checks: &[(CheckButton, Vec<Field>)]) -> Vec<Field> {
+    field_checks
+        .iter()
+        .filter(|(check, _)| check.is_active())
+        .flat_map(|(_, fields)| fields.clone())
+        .collect()
+}
+
+#[allow(clippy::large_enum_variant)]
+enum ScanMessage {
+    Track(PathBuf, Result<MetadataContainer, MetadataError>),
+    Failed(PathBuf, String),
+    Finished,
+}
+
+// Runs off the main thread, reading each track's tags as soon as it's
+// found. Nothing is sent once the window stops listening
+fn scan_tracks(
+    paths: &[PathBuf],
+    options: &ScanOptions,
+    cancelled: &AtomicBool,
+    sender: &glib::Sender<ScanMessage>,
+) {
+    let agent = MetadataAgent::new();
+    visit_audio_files(paths, options, cancelled, |entry| {
+        let message = match entry {
+            Ok(path) => {
+                let metadata = agent.metadata(&path);
+                ScanMessage::Track(path, metadata)
+            }
+            Err((path, e)) => ScanMessage::Failed(path, e.to_string()),
+        };
+        if sender.send(message).is_err() {
+            cancelled.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let _ = sender.send(ScanMessage::Finished);
+}